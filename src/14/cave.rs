@@ -0,0 +1,782 @@
+// Cave simulation shared by both parts of the puzzle. Part 1 defaults to sand
+// falling into the abyss, and part 2 to an infinite floor, but every mode can
+// be picked in both of them.
+//
+// Options:
+//   --floor MODE        what lies below the rocks (default depends on the part):
+//                         abyss:    nothing, sand falls forever
+//                         infinite: a floor spanning infinitely to both sides
+//                         finite:   a floor as wide as the rocks, sand falls
+//                                   into the abyss from its edges
+//   --floor-depth N     distance between the lowest rock and the floor (default 2)
+//   --source X,Y        where sand comes from (default 500,0). Repeat it to pour
+//                       sand from more than one source, taking turns.
+//   --steps             draw every step of every grain of sand falling. Very nice
+//                       for debugging, but too slow for the actual puzzle input.
+//   --every N           record a frame every N grains of sand at rest (default 1)
+//   --animate           play the recorded frames in the terminal, with colors
+//   --fps N             frame rate of the terminal animation (default 30)
+//   --ppm DIR           write the recorded frames into DIR as a sequence of PPM images
+//   --sweep             count the sand sweeping the cave row by row instead of
+//                       simulating every grain. Only with an infinite floor.
+
+use std::cmp;
+use std::env;
+use std::fmt;
+use std::fs;
+use std::ops::Range;
+use std::thread;
+use std::time::Duration;
+
+// Size in pixels of each cell in the PPM frames
+const PPM_SCALE: usize = 4;
+
+const DEFAULT_SOURCE: Point = Point(500, 0);
+const DEFAULT_FLOOR_DEPTH: u32 = 2;
+
+#[derive(Debug, Eq, Hash, PartialEq, Clone, Copy)]
+pub struct Point(pub u32, pub u32);
+
+#[derive(Debug, Clone, Copy)]
+enum Object {
+    Rock,
+    Sand,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Bounds {
+    min_x: u32,
+    max_x: u32,
+    min_y: u32,
+    max_y: u32,
+}
+
+#[derive(Debug, Eq, PartialEq, Clone, Copy)]
+pub enum Floor {
+    Abyss,
+    Infinite,
+    Finite,
+}
+
+#[derive(Debug)]
+struct Source {
+    point: Point,
+
+    // Path followed by the last grain of sand from this source. The next grain
+    // follows the exact same path until the point where the last one came to
+    // rest, so we can resume from there instead of starting again at the top.
+    path: Vec<Point>,
+
+    blocked: bool,
+}
+
+// The grid covers the rocks from the top down to the row above the floor (or
+// down to the lowest rock when there's no floor). Sand can only leave it by
+// falling into the abyss, except with an infinite floor: there sand piles up in
+// a triangle below each source, so it can never spread more than
+// `floor_y - source_y - 1` columns to either side of it, and the grid is made
+// wide enough to fit these triangles. When a triangle would reach x=0, every
+// point is shifted to the right so that it fits with a column to spare, since
+// sand moving left of x=0 would otherwise be taken for falling into the abyss.
+#[derive(Debug)]
+pub struct Cave {
+    grid: Vec<Option<Object>>,
+    grid_min_x: u32,
+    width: u32,
+    height: u32,
+    bounds: Bounds,
+    floor: Floor,
+    floor_depth: u32,
+    sources: Vec<Source>,
+    next_source: usize,
+    draw_while_falling: bool,
+}
+
+#[derive(Debug)]
+pub struct Options {
+    pub floor: Floor,
+    pub floor_depth: u32,
+    pub sources: Vec<Point>,
+    pub steps: bool,
+    pub every: u32,
+    pub animate: bool,
+    pub fps: u32,
+    pub ppm_dir: Option<String>,
+    pub sweep: bool,
+}
+
+#[derive(Debug)]
+pub enum ParseError {
+    NoRocks,
+    SourceBelowFloor(Point),
+    InvalidPath {
+        line_number: usize,
+        line: String,
+        span: Range<usize>,
+        message: String,
+    },
+}
+
+#[derive(Debug)]
+pub struct Recorder {
+    pub options: Options,
+    frames: u32,
+}
+
+impl Point {
+    // Points from this one to `other`, both included. Rocks can only form
+    // horizontal, vertical or 45 degree diagonal lines.
+    fn path_to(&self, other: &Point) -> Option<Vec<Point>> {
+        let Point(x, y) = *self;
+        let Point(other_x, other_y) = *other;
+
+        let (distance_x, distance_y) = (x.abs_diff(other_x), y.abs_diff(other_y));
+        if distance_x != 0 && distance_y != 0 && distance_x != distance_y {
+            return None;
+        }
+
+        let steps = cmp::max(distance_x, distance_y);
+        let path = (0..=steps)
+            .map(|i| Point(step_towards(x, other_x, i), step_towards(y, other_y, i)))
+            .collect();
+
+        Some(path)
+    }
+}
+
+impl Object {
+    fn to_char(self) -> char {
+        match self {
+            Object::Rock => '#',
+            Object::Sand => 'o',
+        }
+    }
+}
+
+impl Cave {
+    pub fn new(input: &str, options: &Options) -> Result<Cave, ParseError> {
+        let rocks = parse_rocks(input)?;
+
+        let mut bounds = None;
+        for point in &rocks {
+            bounds = Some(Bounds::including(bounds, point));
+        }
+
+        let mut cave = Cave {
+            grid: vec![],
+            grid_min_x: 0,
+            width: 0,
+            height: 0,
+            bounds: bounds.ok_or(ParseError::NoRocks)?,
+            floor: options.floor,
+            floor_depth: options.floor_depth,
+            sources: vec![],
+            next_source: 0,
+            draw_while_falling: options.steps,
+        };
+
+        for &source in &options.sources {
+            if cave.floor != Floor::Abyss && source.1 >= cave.floor_y() {
+                return Err(ParseError::SourceBelowFloor(source));
+            }
+        }
+
+        let shift_x = match cave.floor {
+            Floor::Infinite => options
+                .sources
+                .iter()
+                .map(|&Point(x, y)| (cave.floor_y() - y).saturating_sub(x))
+                .max()
+                .unwrap_or(0),
+            Floor::Abyss | Floor::Finite => 0,
+        };
+        let shift = |Point(x, y): Point| Point(x + shift_x, y);
+
+        cave.bounds.min_x += shift_x;
+        cave.bounds.max_x += shift_x;
+        let mut grid_min_x = cave.bounds.min_x;
+        let mut grid_max_x = cave.bounds.max_x;
+
+        for &point in &options.sources {
+            let point = shift(point);
+            let Point(source_x, source_y) = point;
+
+            if cave.floor == Floor::Infinite {
+                let spread = cave.floor_y() - source_y - 1;
+                grid_min_x = cmp::min(grid_min_x, source_x - spread);
+                grid_max_x = cmp::max(grid_max_x, source_x + spread);
+            }
+
+            cave.sources.push(Source {
+                point,
+                path: vec![],
+                blocked: false,
+            });
+        }
+
+        cave.grid_min_x = grid_min_x;
+        cave.width = grid_max_x - grid_min_x + 1;
+        cave.height = match cave.floor {
+            Floor::Abyss => cave.bounds.max_y + 1,
+            Floor::Infinite | Floor::Finite => cave.floor_y(),
+        };
+        cave.grid = vec![None; (cave.width * cave.height) as usize];
+
+        for &point in &rocks {
+            cave.set(&shift(point), Object::Rock);
+        }
+
+        Ok(cave)
+    }
+
+    fn index(&self, point: &Point) -> Option<usize> {
+        let Point(x, y) = *point;
+
+        if x < self.grid_min_x || x - self.grid_min_x >= self.width || y >= self.height {
+            return None;
+        }
+
+        Some((y * self.width + (x - self.grid_min_x)) as usize)
+    }
+
+    fn get(&self, point: &Point) -> Option<Object> {
+        self.index(point).and_then(|i| self.grid[i])
+    }
+
+    fn set(&mut self, point: &Point, object: Object) {
+        let i = self.index(point).unwrap();
+        self.grid[i] = Some(object);
+    }
+
+    // Drops a grain of sand from the next source that isn't blocked yet.
+    // Returns false once a grain falls into the abyss, or when every source is
+    // blocked and no more sand can come in.
+    pub fn drop_sand(&mut self) -> bool {
+        for _ in 0..self.sources.len() {
+            let i = self.next_source;
+            self.next_source = (i + 1) % self.sources.len();
+
+            if self.sources[i].blocked {
+                continue;
+            }
+
+            // Sand from other sources may have come to rest on the path
+            while let Some(point) = self.sources[i].path.last() {
+                if self.get(point).is_none() {
+                    break;
+                }
+                self.sources[i].path.pop();
+            }
+
+            if self.sources[i].path.is_empty() {
+                let point = self.sources[i].point;
+
+                if self.has_object(&point) {
+                    self.sources[i].blocked = true;
+                    continue;
+                }
+
+                self.sources[i].path.push(point);
+            }
+
+            return self.fall(i);
+        }
+
+        false
+    }
+
+    fn fall(&mut self, source: usize) -> bool {
+        loop {
+            let sand = *self.sources[source].path.last().unwrap();
+            let Point(x, y) = sand;
+
+            if self.draw_while_falling {
+                println!("{}", self.render(Some(&sand), false));
+            }
+
+            let mut next = None;
+            for x in [Some(x), x.checked_sub(1), Some(x + 1)] {
+                let point = match x {
+                    Some(x) => Point(x, y + 1),
+                    None => return false,
+                };
+
+                if self.has_object(&point) {
+                    continue;
+                }
+
+                // Left the grid, reached abyss
+                if self.index(&point).is_none() {
+                    return false;
+                }
+
+                next = Some(point);
+                break;
+            }
+
+            match next {
+                Some(point) => self.sources[source].path.push(point),
+                None => {
+                    // Nowhere to keep falling, came to rest
+                    self.set(&sand, Object::Sand);
+                    self.sources[source].path.pop();
+                    return true;
+                }
+            }
+        }
+    }
+
+    // Counts how much sand will come to rest without simulating every grain.
+    // Only possible with an infinite floor: a cell ends up with sand if it's
+    // not a rock and it's either a source or any of the three cells above it
+    // (diagonals included) has sand, so we can sweep row by row from the
+    // highest source down to the floor.
+    pub fn count_reachable(&self) -> Option<u32> {
+        if self.floor != Floor::Infinite {
+            return None;
+        }
+
+        let start_y = self.sources.iter().map(|s| s.point.1).min()?;
+        let width = self.width as usize;
+        let mut row = vec![false; width];
+        let mut count = 0;
+
+        for y in start_y..self.floor_y() {
+            let mut next = vec![false; width];
+
+            for i in 0..width {
+                let point = Point(self.grid_min_x + i as u32, y);
+
+                let above = row[i] || (i > 0 && row[i - 1]) || (i + 1 < width && row[i + 1]);
+                let source = self.sources.iter().any(|s| s.point == point);
+
+                if (above || source) && !matches!(self.get(&point), Some(Object::Rock)) {
+                    next[i] = true;
+                    count += 1;
+                }
+            }
+
+            row = next;
+        }
+
+        Some(count)
+    }
+
+    fn has_object(&self, point: &Point) -> bool {
+        self.get(point).is_some() || self.touches_floor(point)
+    }
+
+    fn touches_floor(&self, point: &Point) -> bool {
+        let Point(x, y) = *point;
+
+        match self.floor {
+            Floor::Abyss => false,
+            Floor::Infinite => y == self.floor_y(),
+            Floor::Finite => {
+                y == self.floor_y() && x >= self.bounds.min_x && x <= self.bounds.max_x
+            }
+        }
+    }
+
+    fn floor_y(&self) -> u32 {
+        self.bounds.max_y + self.floor_depth
+    }
+
+    pub fn draw(&self) {
+        print!("{}", self.render(None, false));
+    }
+
+    // With an infinite floor, the view is as wide as the grid, which fits the
+    // whole pile of sand
+    fn view(&self) -> (Range<i32>, Range<i32>) {
+        let margin_y = match self.floor {
+            Floor::Abyss => 3,
+            Floor::Infinite | Floor::Finite => self.floor_depth as i32 + 1,
+        };
+
+        let range_x = match self.floor {
+            Floor::Abyss => (self.bounds.min_x as i32 - 3)..(self.bounds.max_x as i32 + 3),
+            Floor::Finite => (self.bounds.min_x as i32 - 10)..(self.bounds.max_x as i32 + 10),
+            Floor::Infinite => self.grid_min_x as i32..(self.grid_min_x + self.width) as i32,
+        };
+        // Sand piles up from the sources, which can be above the rocks
+        let min_y = self
+            .sources
+            .iter()
+            .map(|s| s.point.1)
+            .fold(self.bounds.min_y, cmp::min);
+        let range_y = (min_y as i32 - 3)..(self.bounds.max_y as i32 + margin_y);
+
+        (range_x, range_y)
+    }
+
+    fn char_at(&self, point: &Point, falling: Option<&Point>) -> char {
+        if self.touches_floor(point) {
+            '='
+        } else if falling == Some(point) {
+            Object::Sand.to_char()
+        } else {
+            match self.get(point) {
+                Some(obj) => obj.to_char(),
+                None => '.',
+            }
+        }
+    }
+
+    fn render(&self, falling: Option<&Point>, colored: bool) -> String {
+        let (range_x, range_y) = self.view();
+        let mut output = String::new();
+
+        for y in range_y {
+            for x in range_x.clone() {
+                let char = self.char_at(&Point(x as u32, y as u32), falling);
+
+                if colored {
+                    let [r, g, b] = color(char);
+                    output.push_str(&format!("\x1b[38;2;{r};{g};{b}m{char}"));
+                } else {
+                    output.push(char);
+                }
+            }
+
+            if colored {
+                output.push_str("\x1b[0m");
+            }
+            output.push('\n');
+        }
+
+        output
+    }
+
+    fn to_ppm(&self) -> Vec<u8> {
+        let (range_x, range_y) = self.view();
+        let width = range_x.len() * PPM_SCALE;
+        let height = range_y.len() * PPM_SCALE;
+
+        let mut ppm = format!("P6\n{width} {height}\n255\n").into_bytes();
+
+        for y in range_y {
+            let row: Vec<u8> = range_x
+                .clone()
+                .flat_map(|x| {
+                    let char = self.char_at(&Point(x as u32, y as u32), None);
+                    color(char).repeat(PPM_SCALE)
+                })
+                .collect();
+
+            for _ in 0..PPM_SCALE {
+                ppm.extend(&row);
+            }
+        }
+
+        ppm
+    }
+}
+
+impl Bounds {
+    fn including(bounds: Option<Bounds>, point: &Point) -> Bounds {
+        let Point(x, y) = *point;
+
+        match bounds {
+            None => Bounds {
+                min_x: x,
+                max_x: x,
+                min_y: y,
+                max_y: y,
+            },
+            Some(bounds) => Bounds {
+                min_x: cmp::min(bounds.min_x, x),
+                max_x: cmp::max(bounds.max_x, x),
+                min_y: cmp::min(bounds.min_y, y),
+                max_y: cmp::max(bounds.max_y, y),
+            },
+        }
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ParseError::NoRocks => write!(f, "No rock paths found in the input"),
+            ParseError::SourceBelowFloor(Point(x, y)) => {
+                write!(f, "Source {x},{y} is not above the floor")
+            }
+            ParseError::InvalidPath {
+                line_number,
+                line,
+                span,
+                message,
+            } => {
+                writeln!(f, "Invalid rock path on line {line_number}: {message}")?;
+                writeln!(f, "  {line}")?;
+                write!(f, "  {}{}", " ".repeat(span.start), "^".repeat(span.len()))
+            }
+        }
+    }
+}
+
+impl Options {
+    pub fn from_args(floor: Floor) -> Options {
+        let mut options = Options {
+            floor,
+            floor_depth: DEFAULT_FLOOR_DEPTH,
+            sources: vec![],
+            steps: false,
+            every: 1,
+            animate: false,
+            fps: 30,
+            ppm_dir: None,
+            sweep: false,
+        };
+
+        let mut args = env::args().skip(1);
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--floor" => options.floor = parse_floor(&arg, args.next()),
+                "--floor-depth" => options.floor_depth = parse_number(&arg, args.next()),
+                "--source" => options.sources.push(
+                    args.next()
+                        .as_deref()
+                        .and_then(parse_point)
+                        .expect("--source expects a point like 500,0"),
+                ),
+                "--steps" => options.steps = true,
+                "--animate" => options.animate = true,
+                "--every" => options.every = parse_number(&arg, args.next()),
+                "--fps" => options.fps = parse_number(&arg, args.next()),
+                "--ppm" => options.ppm_dir = Some(args.next().expect("--ppm expects a directory")),
+                "--sweep" => options.sweep = true,
+                _ => panic!("Unknown option {arg}"),
+            }
+        }
+
+        if options.sources.is_empty() {
+            options.sources.push(DEFAULT_SOURCE);
+        }
+
+        options
+    }
+}
+
+impl Recorder {
+    pub fn new(options: Options) -> Recorder {
+        if let Some(dir) = &options.ppm_dir {
+            fs::create_dir_all(dir).unwrap();
+        }
+
+        Recorder { options, frames: 0 }
+    }
+
+    pub fn sand_rested(&mut self, cave: &Cave, units: u32) {
+        if units.is_multiple_of(self.options.every) {
+            self.record(cave);
+        }
+    }
+
+    pub fn record(&mut self, cave: &Cave) {
+        if self.options.animate {
+            // Clear the screen and move the cursor back to the top
+            print!("\x1b[2J\x1b[H{}", cave.render(None, true));
+            thread::sleep(Duration::from_secs_f64(1.0 / self.options.fps as f64));
+        }
+
+        if let Some(dir) = &self.options.ppm_dir {
+            let path = format!("{dir}/frame_{:05}.ppm", self.frames);
+            fs::write(path, cave.to_ppm()).unwrap();
+        }
+
+        self.frames += 1;
+    }
+}
+
+fn color(char: char) -> [u8; 3] {
+    match char {
+        '#' => [140, 140, 140],
+        'o' => [230, 190, 80],
+        '=' => [150, 80, 40],
+        _ => [50, 50, 50],
+    }
+}
+
+fn parse_floor(option: &str, value: Option<String>) -> Floor {
+    match value.as_deref() {
+        Some("abyss") => Floor::Abyss,
+        Some("infinite") => Floor::Infinite,
+        Some("finite") => Floor::Finite,
+        _ => panic!("{option} expects one of abyss, infinite or finite"),
+    }
+}
+
+fn parse_number(option: &str, value: Option<String>) -> u32 {
+    value
+        .and_then(|value| value.parse().ok())
+        .filter(|value| *value > 0)
+        .unwrap_or_else(|| panic!("{option} expects a positive number"))
+}
+
+fn parse_point(point: &str) -> Option<Point> {
+    let (x, y) = point.trim().split_once(',')?;
+    Some(Point(x.trim().parse().ok()?, y.trim().parse().ok()?))
+}
+
+fn parse_rocks(input: &str) -> Result<Vec<Point>, ParseError> {
+    let mut rocks = vec![];
+
+    for (i, line) in input.lines().enumerate() {
+        let line = line.trim_end();
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        let error = |span: Range<usize>, message: String| ParseError::InvalidPath {
+            line_number: i + 1,
+            line: line.to_string(),
+            span,
+            message,
+        };
+
+        // Points in the path along with where they are in the line
+        let mut points: Vec<(Point, Range<usize>)> = vec![];
+        let mut offset = 0;
+
+        for token in line.split("->") {
+            let start = offset + (token.len() - token.trim_start().len());
+            let span = start..(start + token.trim().len());
+            offset += token.len() + "->".len();
+
+            let point = match parse_point(token) {
+                Some(point) => point,
+                None => {
+                    let message = format!("expected a point like 498,4, found {:?}", token.trim());
+                    return Err(error(span, message));
+                }
+            };
+
+            match points.last() {
+                // A path with a single point is a single rock
+                None => rocks.push(point),
+                Some((previous, previous_span)) => match previous.path_to(&point) {
+                    Some(path) => rocks.extend(path),
+                    None => {
+                        let span = previous_span.start..span.end;
+                        let message = format!(
+                            "segment {} is neither horizontal, vertical nor diagonal",
+                            points.len()
+                        );
+                        return Err(error(span, message));
+                    }
+                },
+            }
+
+            points.push((point, span));
+        }
+    }
+
+    Ok(rocks)
+}
+
+// Moves `steps` units from `from` towards `to`, without going past it
+fn step_towards(from: u32, to: u32, steps: u32) -> u32 {
+    if to >= from {
+        from + cmp::min(steps, to - from)
+    } else {
+        from - cmp::min(steps, from - to)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EXAMPLE: &str = "498,4 -> 498,6 -> 496,6\n503,4 -> 502,4 -> 502,9 -> 494,9\n";
+
+    fn infinite_floor(input: &str, floor_depth: u32, sources: &[Point]) -> Cave {
+        Cave::new(input, &infinite_floor_options(floor_depth, sources)).unwrap()
+    }
+
+    fn infinite_floor_options(floor_depth: u32, sources: &[Point]) -> Options {
+        Options {
+            floor: Floor::Infinite,
+            floor_depth,
+            sources: sources.to_vec(),
+            steps: false,
+            every: 1,
+            animate: false,
+            fps: 30,
+            ppm_dir: None,
+            sweep: false,
+        }
+    }
+
+    fn simulate(cave: &mut Cave) -> u32 {
+        let mut units = 0;
+        while cave.drop_sand() {
+            units += 1;
+        }
+        units
+    }
+
+    #[test]
+    fn example() {
+        let mut cave = infinite_floor(EXAMPLE, DEFAULT_FLOOR_DEPTH, &[DEFAULT_SOURCE]);
+        assert_eq!(cave.count_reachable(), Some(93));
+        assert_eq!(simulate(&mut cave), 93);
+    }
+
+    #[test]
+    fn row_sweep_matches_simulation() {
+        let variants: [(&str, u32, &[Point]); 7] = [
+            (EXAMPLE, 2, &[DEFAULT_SOURCE]),
+            (EXAMPLE, 5, &[DEFAULT_SOURCE]),
+            (EXAMPLE, 2, &[Point(497, 1)]),
+            (EXAMPLE, 3, &[DEFAULT_SOURCE, Point(490, 2)]),
+            (EXAMPLE, 4, &[Point(499, 3), Point(503, 0), Point(507, 6)]),
+            ("5,5 -> 6,5", 2, &[Point(2, 0)]),
+            ("5,5 -> 6,5", 2, &[Point(0, 3), Point(9, 0)]),
+        ];
+
+        for (input, floor_depth, sources) in variants {
+            let mut cave = infinite_floor(input, floor_depth, sources);
+            let reachable = cave.count_reachable();
+
+            assert_eq!(
+                Some(simulate(&mut cave)),
+                reachable,
+                "floor depth {floor_depth}, sources {sources:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn infinite_floor_left_of_zero() {
+        // The triangle below the source spreads 6 columns to the left of it,
+        // the two rocks take a cell each and shade nothing
+        let mut cave = infinite_floor("5,5 -> 6,5", DEFAULT_FLOOR_DEPTH, &[Point(2, 0)]);
+        assert_eq!(simulate(&mut cave), 49 - 2);
+    }
+
+    #[test]
+    fn source_below_floor() {
+        let options = infinite_floor_options(DEFAULT_FLOOR_DEPTH, &[Point(1, 11)]);
+        let error = Cave::new(EXAMPLE, &options).unwrap_err();
+        assert!(matches!(error, ParseError::SourceBelowFloor(Point(1, 11))));
+        assert_eq!(error.to_string(), "Source 1,11 is not above the floor");
+    }
+
+    #[test]
+    fn view_fits_the_pile() {
+        let variants: [(&str, u32, &[Point]); 3] = [
+            (EXAMPLE, 2, &[DEFAULT_SOURCE]),
+            (EXAMPLE, 9, &[Point(490, 2), Point(510, 0)]),
+            ("5,5 -> 6,5", 2, &[Point(2, 0)]),
+        ];
+
+        for (input, floor_depth, sources) in variants {
+            let mut cave = infinite_floor(input, floor_depth, sources);
+            let units = simulate(&mut cave);
+            let grains = cave.render(None, false).matches('o').count();
+
+            assert_eq!(grains as u32, units, "sources {sources:?}");
+        }
+    }
+}
@@ -1,441 +1,327 @@
+// Usage: 2.rs [options] [rocks]
+//
+// Prints the height of the tower after the given number of rocks (by default
+// a trillion of them).
+//
+// Options:
+//   --width N      width of the chamber, up to 16 (default 7)
+//   --spawn-x N    distance between the left wall and new rocks (default 2)
+//   --spawn-y N    distance between the top of the tower and new rocks (default 3)
+//   --summary      print where the cycle starts, its length and height gain
+//   --csv FILE     write the height after each rock to FILE, along with the
+//                  height gained, and the shape and first jet of each rock.
+//                  Covers the rocks until the cycle has repeated twice.
+//
+// Besides the `<` and `>` jets, the input may have `(` and `)` to rotate the
+// falling rock counter-clockwise or clockwise instead of pushing it.
+//
+// With `--inspect N`, the simulation fast-forwards to rock N and then shows the
+// chamber step by step, reading commands from the standard input (`h` lists
+// them).
+
+mod tetris;
+
+use std::cmp;
 use std::collections::HashMap;
-use std::collections::HashSet;
-use std::collections::VecDeque;
-use std::fmt;
+use std::env;
 use std::fs;
+use std::process;
+use tetris::{parse_number, Config, Directions, Inspector, Row, ShapeFactory, Tetris};
 
-const DEBUG: bool = false;
-
-type Point = (i32, i32);
-
-#[derive(Debug, Copy, Clone, Eq, PartialEq)]
-enum Direction {
-    Left,
-    Right,
-}
-
-#[derive(Debug)]
-struct Directions {
-    items: VecDeque<Direction>,
-    current: usize,
-}
-
-#[derive(Debug, Clone, Eq, PartialEq)]
-struct Shape {
-    points: Vec<Point>,
-}
-
-#[derive(Debug)]
-struct ShapeFactory {
-    shapes: Vec<Shape>,
-    current: usize,
-}
+// Rocks simulated at most while looking for a cycle
+const MAX_SEARCHED_ROCKS: u64 = 1_000_000;
 
+// Everything that determines how the tower grows from some point on: the next
+// shape and jet, plus the rows at the top of the tower that rocks can still
+// reach (see `Tetris::surface`).
 #[derive(Hash, Debug, Eq, PartialEq)]
-struct Seen {
-    direction_index: usize,
+struct State {
     shape_index: usize,
-    row: String,
-}
-
-#[derive(Debug)]
-struct SeenWhen {
-    round_number: u32,
-    height: i32,
+    direction_index: usize,
+    surface: Vec<Row>,
 }
 
 #[derive(Debug)]
-struct Cycle {
+struct RockStats {
+    // Height of the tower once the rock comes to rest
     height: u64,
-    starts_with_height: u64,
-    starts_on_round: u32,
-    repeats_every: u64,
-    direction_index: usize,
     shape_index: usize,
+    // Jet that pushes the rock first
+    jet_index: usize,
 }
 
 #[derive(Debug)]
-struct Tetris {
-    falling_shape: Option<Shape>,
-    resting_points: HashSet<Point>,
-    seen: HashMap<Seen, SeenWhen>,
-    cycle_found: Option<Cycle>,
-    round_number: u32,
-    max_x: i32,
-    max_y: i32,
-}
-
-impl Direction {
-    fn from_char(value: char) -> Direction {
-        match value {
-            '<' => Direction::Left,
-            '>' => Direction::Right,
-            _ => panic!("Invalid direction {}", value),
-        }
-    }
+struct Cycle {
+    // Number of rocks after which the cycle starts
+    start: u64,
+    length: u64,
+    height_gain: u64,
 }
 
-impl fmt::Display for Direction {
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        match *self {
-            Direction::Left => write!(f, "Left"),
-            Direction::Right => write!(f, "Right"),
-        }
-    }
-}
+impl Cycle {
+    // Simulates rocks until the tower gets into a state it has been in before,
+    // or until `max_rocks` (at most MAX_SEARCHED_ROCKS) have fallen. Returns the
+    // height of the tower after each rock, up to that point.
+    fn find(
+        shape_factory: &ShapeFactory,
+        directions: &Directions,
+        config: &Config,
+        max_rocks: u64,
+    ) -> (Option<Cycle>, Vec<u64>) {
+        let mut shape_factory = shape_factory.clone();
+        let mut directions = directions.clone();
+        let mut tetris = Tetris::new(config.clone());
+
+        let mut seen: HashMap<State, u64> = HashMap::new();
+        let mut heights = vec![0];
+
+        for rocks in 1..=cmp::min(max_rocks, MAX_SEARCHED_ROCKS) {
+            tetris.round(&mut shape_factory, &mut directions);
+            heights.push(tetris.max_y as u64);
+
+            let surface = match tetris.surface() {
+                Some(surface) => surface,
+                None => continue,
+            };
 
-impl Directions {
-    fn from_str(value: &str) -> Directions {
-        let items = value
-            .trim()
-            .chars()
-            .map(|c| Direction::from_char(c))
-            .collect();
+            let state = State {
+                shape_index: shape_factory.current,
+                direction_index: directions.current,
+                surface,
+            };
 
-        Directions { items, current: 0 }
-    }
+            if let Some(&start) = seen.get(&state) {
+                let cycle = Cycle {
+                    start,
+                    length: rocks - start,
+                    height_gain: heights[rocks as usize] - heights[start as usize],
+                };
 
-    fn next(&mut self) -> Direction {
-        let current = self.items[self.current];
+                return (Some(cycle), heights);
+            }
 
-        self.current += 1;
-        if self.current == self.items.len() {
-            self.current = 0;
+            seen.insert(state, rocks);
         }
 
-        current
-    }
-}
-
-impl Shape {
-    fn new() -> Shape {
-        Shape { points: vec![] }
-    }
-
-    fn move_offset(&mut self, offset_x: i32, offset_y: i32) {
-        for i in 0..self.points.len() {
-            let (x, y) = self.points[i];
-            self.points[i] = (x + offset_x, y + offset_y);
-        }
+        (None, heights)
     }
 
-    fn move_to(&mut self, direction: Direction) {
-        match direction {
-            Direction::Left => self.move_x(-1),
-            Direction::Right => self.move_x(1),
+    fn height_after(&self, heights: &[u64], rocks: u64) -> u64 {
+        if rocks < self.start {
+            return heights[rocks as usize];
         }
-    }
 
-    fn move_x(&mut self, offset: i32) {
-        self.move_offset(offset, 0);
-    }
+        let cycles = (rocks - self.start) / self.length;
+        let remaining = (rocks - self.start) % self.length;
 
-    fn move_y(&mut self, offset: i32) {
-        self.move_offset(0, offset);
+        heights[(self.start + remaining) as usize] + cycles * self.height_gain
     }
 }
 
-impl ShapeFactory {
-    fn from_str(input: &str) -> ShapeFactory {
-        let shapes_str = input.split("\n\n");
-        let mut shapes: Vec<Shape> = vec![];
-
-        for shape in shapes_str {
-            let lines: Vec<String> = shape.lines().map(|v| v.to_string()).collect();
-            let height = lines.len();
-            let mut shape = Shape::new();
-
-            for y in 0..height {
-                let line: Vec<char> = lines[y].chars().collect();
+fn no_cycle(heights: &[u64]) -> String {
+    format!("No cycle found in the first {} rocks", heights.len() - 1)
+}
 
-                for x in 0..line.len() {
-                    if line[x] == '#' {
-                        shape.points.push((x as i32, (height - y - 1) as i32));
-                    }
-                }
-            }
+fn simulate(
+    shape_factory: &ShapeFactory,
+    directions: &Directions,
+    config: &Config,
+    rocks: u64,
+) -> Vec<RockStats> {
+    let mut shape_factory = shape_factory.clone();
+    let mut directions = directions.clone();
+    let mut tetris = Tetris::new(config.clone());
+    let mut stats = vec![];
+
+    for _ in 0..rocks {
+        let shape_index = shape_factory.current;
+        let jet_index = directions.current;
 
-            shapes.push(shape);
-        }
+        tetris.round(&mut shape_factory, &mut directions);
 
-        ShapeFactory { shapes, current: 0 }
+        stats.push(RockStats {
+            height: tetris.max_y as u64,
+            shape_index,
+            jet_index,
+        });
     }
 
-    fn produce(&mut self) -> Shape {
-        let current = &self.shapes[self.current];
-
-        self.current += 1;
-        if self.current == self.shapes.len() {
-            self.current = 0;
-        }
+    stats
+}
 
-        current.clone()
+fn write_csv(path: &str, stats: &[RockStats]) {
+    let mut csv = String::from("rock,height,delta,shape_index,jet_index\n");
+    let mut previous_height = 0;
+
+    for (i, rock) in stats.iter().enumerate() {
+        csv.push_str(&format!(
+            "{},{},{},{},{}\n",
+            i + 1,
+            rock.height,
+            rock.height - previous_height,
+            rock.shape_index,
+            rock.jet_index
+        ));
+        previous_height = rock.height;
     }
 
-    fn produce_at(&mut self, (x, y): Point) -> Shape {
-        let mut shape = self.produce();
-        shape.move_x(x);
-        shape.move_y(y);
-        shape
-    }
+    fs::write(path, csv).unwrap();
 }
 
-impl Tetris {
-    fn new() -> Tetris {
-        Tetris {
-            max_x: 6,
-            max_y: 0,
-            resting_points: HashSet::new(),
-            seen: HashMap::new(),
-            falling_shape: None,
-            round_number: 0,
-            cycle_found: None,
-        }
-    }
-
-    fn move_falling_shape_to(&mut self, direction: Direction) -> bool {
-        if self.falling_shape.is_none() {
-            return false;
+fn main() {
+    let directions_input = fs::read_to_string("input.txt").unwrap();
+    let directions = Directions::from_str(&directions_input);
+
+    let args: Vec<String> = env::args().skip(1).collect();
+    let mut config = Config::new();
+    let mut rocks: u64 = 1000000000000;
+    let mut inspect = None;
+    let mut summary = false;
+    let mut csv = None;
+
+    let mut i = 0;
+    while i < args.len() {
+        if let Some(taken) = config.parse_option(&args, i) {
+            i += taken;
+            continue;
         }
 
-        let falling_shape = self.falling_shape.as_ref().unwrap();
-        let mut moved_shape = falling_shape.clone();
-        moved_shape.move_to(direction);
-
-        let has_overflown = moved_shape
-            .points
-            .iter()
-            .any(|&(x, y)| x < 0 || x > self.max_x || self.resting_points.contains(&(x, y)));
-        if has_overflown {
-            return false;
+        match args[i].as_str() {
+            "--summary" => summary = true,
+            "--csv" => {
+                csv = Some(args.get(i + 1).expect("--csv expects a file").clone());
+                i += 1;
+            }
+            "--inspect" => {
+                inspect = Some(parse_number(&args[i], args.get(i + 1)) as u64);
+                i += 1;
+            }
+            value => rocks = value.parse().unwrap(),
         }
-
-        self.falling_shape = Some(moved_shape);
-        true
+        i += 1;
     }
 
-    fn move_falling_shape_down(&mut self) -> bool {
-        if self.falling_shape.is_none() {
-            return false;
-        }
-
-        let falling_shape = self.falling_shape.as_ref().unwrap();
-        let mut moved_shape = falling_shape.clone();
-        moved_shape.move_y(-1);
-
-        let has_overflown = moved_shape
-            .points
-            .iter()
-            .any(|&(x, y)| self.resting_points.contains(&(x, y)) || y < 0);
-        if has_overflown {
-            return false;
+    let shapes_input = fs::read_to_string("shapes.txt").unwrap();
+    let shape_factory = match ShapeFactory::from_str(&shapes_input, &config) {
+        Ok(shape_factory) => shape_factory,
+        Err(error) => {
+            eprintln!("{error}");
+            process::exit(1);
         }
+    };
 
-        self.falling_shape = Some(moved_shape);
-        true
+    if let Some(rock) = inspect {
+        Inspector::new(Tetris::new(config), shape_factory, directions).run(rock);
+        return;
     }
 
-    fn round(&mut self, shape_factory: &mut ShapeFactory, movements: &mut Directions) {
-        self.round_number += 1;
-        self.falling_shape = Some(shape_factory.produce_at((2, self.max_y + 3)));
-
-        if DEBUG {
-            println!("A new rock begins falling:");
-            self.print();
-        }
-
-        loop {
-            let direction = movements.next();
-            if DEBUG {
-                println!("Jet of gas attempts to push rock {}", direction);
-            }
-            self.move_falling_shape_to(direction);
-            if DEBUG {
-                self.print();
-            }
-
-            if self.move_falling_shape_down() {
-                if DEBUG {
-                    println!("Rock falls 1 unit");
-                    self.print();
-                }
-            } else {
-                if DEBUG {
-                    println!("Rock comes to rest");
-                }
-                self.move_falling_shape_to_resting_points();
-                if DEBUG {
-                    self.print();
-                }
-
-                break;
-            }
-        }
-
-        if self.cycle_found.is_none() {
-            let row = self.get_row(self.max_y - 1);
+    let (cycle, heights) = Cycle::find(&shape_factory, &directions, &config, rocks);
 
-            if !row.eq("#######") {
-                return;
-            }
-
-            let shape_index = shape_factory.current;
-            let direction_index = movements.current;
-            let seen = Seen {
-                row,
-                shape_index,
-                direction_index,
-            };
-
-            if self.seen.contains_key(&seen) {
-                println!("FOUND!!!!");
-                // dbg!(&seen);
-                let when = self.seen.get(&seen).unwrap();
-                dbg!(&seen);
-                dbg!(&self.round_number);
-                dbg!(&when);
-                // self.print();
-                // self.cycle_found = true;
-
-                self.cycle_found = Some(Cycle {
-                    height: (self.max_y - when.height) as u64,
-                    starts_with_height: when.height as u64,
-                    starts_on_round: when.round_number,
-                    repeats_every: (self.round_number - when.round_number) as u64,
-                    direction_index: direction_index,
-                    shape_index: shape_index,
-                });
-            } else {
-                self.seen.insert(
-                    seen,
-                    SeenWhen {
-                        round_number: self.round_number,
-                        height: self.max_y,
-                    },
+    if summary {
+        match &cycle {
+            Some(cycle) => {
+                println!(
+                    "Cycle starts after rock {}, at height {}",
+                    cycle.start, heights[cycle.start as usize]
+                );
+                println!(
+                    "Repeats every {} rocks, adding {} to the height",
+                    cycle.length, cycle.height_gain
                 );
             }
+            None => println!("{}", no_cycle(&heights)),
         }
     }
 
-    fn move_falling_shape_to_resting_points(&mut self) -> bool {
-        if self.falling_shape.is_none() {
-            return false;
-        }
-
-        let falling_shape = self.falling_shape.as_ref().unwrap();
-        for point in &falling_shape.points {
-            self.resting_points.insert(*point);
-        }
-
-        let max_y = falling_shape
-            .points
-            .iter()
-            .map(|&(_x, y)| y)
-            .max()
-            .unwrap_or(0)
-            + 1;
-        if max_y > self.max_y {
-            self.max_y = max_y;
-        }
-
-        self.falling_shape = None;
-        true
+    if let Some(path) = csv {
+        let length = match &cycle {
+            Some(cycle) => cmp::min(rocks, cycle.start + 2 * cycle.length),
+            None => heights.len() as u64 - 1,
+        };
+        write_csv(
+            &path,
+            &simulate(&shape_factory, &directions, &config, length),
+        );
     }
 
-    fn get_row(&self, y: i32) -> String {
-        let empty_shape = Shape::new();
-        let falling_shape = self.falling_shape.as_ref().unwrap_or(&empty_shape);
-        let mut result: Vec<char> = vec![];
-
-        for x in 0..=self.max_x {
-            let point = (x, y);
+    let height = match &cycle {
+        Some(cycle) => cycle.height_after(&heights, rocks),
+        None => match heights.get(rocks as usize) {
+            Some(&height) => height,
+            None => {
+                eprintln!(
+                    "{}, can't tell the height after {rocks}",
+                    no_cycle(&heights)
+                );
+                process::exit(1);
+            }
+        },
+    };
 
-            let piece = if falling_shape.points.contains(&point) {
-                '@'
-            } else if self.resting_points.contains(&point) {
-                '#'
-            } else {
-                '.'
-            };
+    println!("{height}");
+}
 
-            result.push(piece);
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SHAPES: &str = include_str!("shapes.txt");
+    const EXAMPLE: &str = ">>><<><>><<<>><>>><<<>>><<<><<<>><>><<>>";
+
+    // Compares the heights found through the cycle against simulating every rock
+    fn check_heights(jets: &str, width: usize, rocks: u64) {
+        let mut config = Config::new();
+        let args = [String::from("--width"), width.to_string()];
+        config.parse_option(&args, 0);
+
+        let shape_factory = ShapeFactory::from_str(SHAPES, &config).unwrap();
+        let directions = Directions::from_str(jets);
+
+        let expected = simulate(&shape_factory, &directions, &config, rocks);
+        let (cycle, heights) = Cycle::find(&shape_factory, &directions, &config, rocks);
+        let cycle =
+            cycle.unwrap_or_else(|| panic!("no cycle for {jets} in a chamber {width} wide"));
+
+        for (i, rock) in expected.iter().enumerate() {
+            let rocks = i as u64 + 1;
+            assert_eq!(
+                cycle.height_after(&heights, rocks),
+                rock.height,
+                "height after {rocks} rocks for {jets} in a chamber {width} wide"
+            );
         }
-
-        result.iter().collect()
     }
 
-    fn print(&self) {
-        let y_margin = 5;
-
-        for reverse_y in 0..=(self.max_y + y_margin) {
-            let y = (self.max_y + y_margin) - reverse_y;
-
-            print!("{:01$} ", y, 6);
-            print!("|{}|", self.get_row(y));
-            println!("");
-        }
-
-        print!("       +");
-        for _ in 0..=self.max_x {
-            print!("-");
-        }
-        print!("+\n\n");
+    #[test]
+    fn example() {
+        check_heights(EXAMPLE, 7, 200_000);
     }
-}
-
-fn main() {
-    let shapes_input = fs::read_to_string("shapes.txt").unwrap();
-    let mut shape_factory = ShapeFactory::from_str(&shapes_input);
-
-    let directions_input = fs::read_to_string("input.txt").unwrap();
-    let mut directions = Directions::from_str(&directions_input);
 
-    let mut tetris = Tetris::new();
-    while tetris.cycle_found.is_none() {
-        tetris.round(&mut shape_factory, &mut directions);
+    #[test]
+    fn puzzle_input() {
+        check_heights(include_str!("input.txt"), 7, 500_000);
     }
 
-    println!("Final tower:");
-    // tetris.print();
-
-    let cycle = tetris.cycle_found.unwrap();
-
-    dbg!(&cycle);
-
-    let asked_round: u64 = 1000000000000;
-
-    // println!("Height: {}", tetris.max_y);
-
-    println!("trying again...");
-
-    // // for _ in 0..16 + 35 + 35 {
-    // //     new_tetris.round(&mut shape_factory, &mut directions);
-    // // }
-    // for _ in 0..561 + 1725 + 1725 {
-    //     new_tetris.round(&mut shape_factory, &mut directions);
-    // }
-    // dbg!(&shape_factory.current);
-    // dbg!(&directions.current);
-    // new_tetris.print();
-
-    let mut height: u64 = cycle.starts_with_height;
-    let mut round = cycle.starts_on_round as u64;
-
-    loop {
-        if round + cycle.repeats_every > asked_round {
-            break;
+    #[test]
+    fn widths() {
+        for width in [6, 8, 9] {
+            check_heights(EXAMPLE, width, 20_000);
+        }
+        for width in [6, 7] {
+            check_heights("<<<>><<>>>><<<<>", width, 20_000);
+        }
+        for width in [6, 7, 8] {
+            check_heights("><<<>>><<>", width, 20_000);
         }
-        height += cycle.height;
-        round += cycle.repeats_every;
     }
 
-    let mut new_tetris = Tetris::new();
-    shape_factory.current = cycle.shape_index;
-    directions.current = cycle.direction_index;
-    for _ in 0..asked_round - round {
-        new_tetris.round(&mut shape_factory, &mut directions);
+    #[test]
+    fn rotations() {
+        for width in [7, 8] {
+            check_heights(">>><(<><>>)<<<>><>)>><<<>>(<<<><<(>><>><<>>", width, 20_000);
+        }
+        check_heights("<<>)<>><(>", 7, 20_000);
+        check_heights("(<<>>)>(<", 6, 20_000);
+        check_heights(">)<<>(>><<", 6, 20_000);
     }
-
-    let result = height + new_tetris.max_y as u64;
-    println!("{}", result);
 }
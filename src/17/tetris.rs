@@ -0,0 +1,919 @@
+// Chamber simulation shared by both parts of the puzzle: the jets of gas, the
+// shapes of the rocks, and the tower they build up as they come to rest.
+
+use std::cmp;
+use std::collections::VecDeque;
+use std::fmt;
+use std::io;
+use std::io::BufRead;
+
+// How often the inspector keeps a copy of the whole simulation, to be able to
+// go back to earlier rocks
+const SNAPSHOT_EVERY: u64 = 1000;
+
+// Rows of the chamber shown by the inspector
+const INSPECT_ROWS: usize = 30;
+
+// Towers whose surface goes deeper than this are not considered for cycles
+#[allow(dead_code)] // Only part 2 looks for cycles
+const MAX_SURFACE_DEPTH: usize = 1000;
+
+pub type Row = u16;
+
+// Rows a shape can span. Shapes are at most as wide as the chamber, and
+// rotating them swaps their width and height.
+const MAX_SHAPE_SIZE: usize = Row::BITS as usize;
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum Direction {
+    Left,
+    Right,
+    Clockwise,
+    CounterClockwise,
+}
+
+#[derive(Debug, Clone)]
+pub struct Directions {
+    items: VecDeque<Direction>,
+    pub current: usize,
+}
+
+// Shapes are copied around on every move, so they are kept small and don't
+// allocate anything
+#[derive(Debug, Clone, Copy)]
+pub struct Shape {
+    // One bit mask per row, from the bottom to the top, of which only the
+    // first `height` are used. Bit `x` is set when the shape fills column `x`
+    // on that row.
+    rows: [Row; MAX_SHAPE_SIZE],
+    height: usize,
+    y: usize,
+    // Position of the shape in the factory, which keeps their names
+    index: usize,
+}
+
+#[derive(Debug, Clone)]
+pub struct ShapeFactory {
+    shapes: Vec<Shape>,
+    names: Vec<Option<String>>,
+    pub current: usize,
+}
+
+#[derive(Debug)]
+pub struct ShapeError {
+    // Position of the shape in the file, starting at 1
+    index: usize,
+    name: Option<String>,
+    line_number: usize,
+    message: String,
+}
+
+#[derive(Debug, Clone)]
+struct Snapshot {
+    tetris: Tetris,
+    shape_factory: ShapeFactory,
+    directions: Directions,
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+enum Step {
+    Push,
+    Fall,
+}
+
+#[derive(Debug)]
+pub struct Inspector {
+    current: Snapshot,
+    // Rocks that came to rest so far
+    rocks: u64,
+    next_step: Step,
+    // Copies of the simulation with 0, SNAPSHOT_EVERY, 2 * SNAPSHOT_EVERY...
+    // rocks at rest
+    snapshots: Vec<Snapshot>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Config {
+    width: usize,
+    spawn_x: usize,
+    spawn_y: usize,
+}
+
+// The chamber is stored as one bit mask per row, like the shapes. Everything
+// below the highest row that is fully blocked can never be reached again, so
+// these rows are dropped and only counted in `pruned_rows`, unless `prune` is
+// turned off to print the whole tower.
+#[derive(Debug, Clone)]
+pub struct Tetris {
+    falling_shape: Option<Shape>,
+    rows: Vec<Row>,
+    pruned_rows: usize,
+    pub prune: bool,
+    config: Config,
+    max_x: usize,
+    pub max_y: usize,
+}
+
+impl Direction {
+    fn from_char(value: char) -> Direction {
+        match value {
+            '<' => Direction::Left,
+            '>' => Direction::Right,
+            ')' => Direction::Clockwise,
+            '(' => Direction::CounterClockwise,
+            _ => panic!("Invalid direction {}", value),
+        }
+    }
+}
+
+impl fmt::Display for Direction {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Direction::Left => write!(f, "Left"),
+            Direction::Right => write!(f, "Right"),
+            Direction::Clockwise => write!(f, "Clockwise"),
+            Direction::CounterClockwise => write!(f, "CounterClockwise"),
+        }
+    }
+}
+
+impl Directions {
+    pub fn from_str(value: &str) -> Directions {
+        let items = value.trim().chars().map(Direction::from_char).collect();
+
+        Directions { items, current: 0 }
+    }
+
+    fn next(&mut self) -> Direction {
+        let current = self.items[self.current];
+
+        self.current += 1;
+        if self.current == self.items.len() {
+            self.current = 0;
+        }
+
+        current
+    }
+}
+
+impl Shape {
+    fn rows(&self) -> &[Row] {
+        &self.rows[..self.height]
+    }
+
+    fn move_to(&mut self, direction: Direction, max_x: usize) -> bool {
+        match direction {
+            Direction::Left => {
+                if self.rows().iter().any(|row| row & 1 != 0) {
+                    return false;
+                }
+                for row in self.rows.iter_mut() {
+                    *row >>= 1;
+                }
+            }
+            Direction::Right => {
+                if self.rows().iter().any(|row| row & (1 << max_x) != 0) {
+                    return false;
+                }
+                for row in self.rows.iter_mut() {
+                    *row <<= 1;
+                }
+            }
+            Direction::Clockwise => return self.rotate(true, max_x),
+            Direction::CounterClockwise => return self.rotate(false, max_x),
+        }
+
+        true
+    }
+
+    // Rotates the shape in place, keeping its bottom left corner where it is
+    fn rotate(&mut self, clockwise: bool, max_x: usize) -> bool {
+        let left = self.rows().iter().map(|row| row.trailing_zeros()).min();
+        let right = self
+            .rows()
+            .iter()
+            .map(|row| Row::BITS - row.leading_zeros())
+            .max();
+
+        let (left, width) = match (left, right) {
+            (Some(left), Some(right)) => (
+                left as usize,
+                (right as usize).saturating_sub(left as usize),
+            ),
+            _ => return true,
+        };
+        let height = self.height;
+
+        if left + height > max_x + 1 {
+            return false;
+        }
+
+        let mut rows = [0; MAX_SHAPE_SIZE];
+        for (y, row) in self.rows().iter().enumerate() {
+            for x in 0..width {
+                if row & (1 << (left + x)) == 0 {
+                    continue;
+                }
+
+                let (new_x, new_y) = if clockwise {
+                    (y, width - 1 - x)
+                } else {
+                    (height - 1 - y, x)
+                };
+                rows[new_y] |= 1 << (left + new_x);
+            }
+        }
+
+        self.rows = rows;
+        self.height = width;
+        true
+    }
+
+    fn moved_to(self, direction: Direction, max_x: usize) -> Option<Shape> {
+        let mut shape = self;
+        if shape.move_to(direction, max_x) {
+            Some(shape)
+        } else {
+            None
+        }
+    }
+
+    fn covers(&self, x: usize, y: usize) -> bool {
+        y >= self.y && y - self.y < self.height && self.rows[y - self.y] & (1 << x) != 0
+    }
+}
+
+impl Shape {
+    // Parses the shape at `index` (starting at 1), along with its name
+    fn parse(
+        block: &[(usize, &str)],
+        index: usize,
+        config: &Config,
+    ) -> Result<(Shape, Option<String>), ShapeError> {
+        let (first_line_number, _) = block[0];
+        let mut lines = block;
+        let mut name = None;
+
+        if let Some(value) = block[0].1.strip_prefix("name:") {
+            name = Some(value.trim().to_string());
+            lines = &block[1..];
+        }
+
+        let error = |line_number: usize, message: String| ShapeError {
+            index,
+            name: name.clone(),
+            line_number,
+            message,
+        };
+
+        // Rock positions, with y growing downwards as in the file
+        let mut rocks: Vec<(usize, usize)> = vec![];
+
+        for (y, &(line_number, line)) in lines.iter().enumerate() {
+            for (x, c) in line.chars().enumerate() {
+                match c {
+                    '#' => rocks.push((x, y)),
+                    '.' => {}
+                    _ => return Err(error(line_number, format!("unexpected character {:?}", c))),
+                }
+            }
+        }
+
+        if rocks.is_empty() {
+            return Err(error(first_line_number, String::from("shape has no rocks")));
+        }
+
+        if !is_connected(&rocks) {
+            return Err(error(
+                first_line_number,
+                String::from("shape is not connected"),
+            ));
+        }
+
+        // Drop any empty rows or columns around the rocks
+        let min_x = rocks.iter().map(|&(x, _)| x).min().unwrap();
+        let max_x = rocks.iter().map(|&(x, _)| x).max().unwrap();
+        let min_y = rocks.iter().map(|&(_, y)| y).min().unwrap();
+        let max_y = rocks.iter().map(|&(_, y)| y).max().unwrap();
+
+        let width = max_x - min_x + 1;
+        if config.spawn_x + width > config.width {
+            let message = format!(
+                "shape is {width} units wide, it doesn't fit a chamber {} units wide when {} units away from the wall",
+                config.width, config.spawn_x
+            );
+            return Err(error(first_line_number, message));
+        }
+
+        let height = max_y - min_y + 1;
+        if height > MAX_SHAPE_SIZE {
+            let message =
+                format!("shape is {height} units tall, shapes can be at most {MAX_SHAPE_SIZE}");
+            return Err(error(first_line_number, message));
+        }
+
+        let mut rows = [0; MAX_SHAPE_SIZE];
+        for &(x, y) in &rocks {
+            rows[max_y - y] |= 1 << (x - min_x);
+        }
+
+        let shape = Shape {
+            rows,
+            height,
+            y: 0,
+            index: index - 1,
+        };
+
+        Ok((shape, name))
+    }
+}
+
+impl fmt::Display for ShapeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.index == 0 {
+            return write!(f, "Invalid shapes: {}", self.message);
+        }
+
+        write!(f, "Invalid shape #{}", self.index)?;
+        if let Some(name) = &self.name {
+            write!(f, " ({name})")?;
+        }
+        write!(f, " on line {}: {}", self.line_number, self.message)
+    }
+}
+
+impl ShapeFactory {
+    // Shapes are drawn with `#` for rock and `.` for empty space, and come
+    // out of the factory in the same order they appear in the file. Blank
+    // lines separate them, and each one may start with a `name: ...` line.
+    // Lines starting with `//` are comments. Every shape needs at least one
+    // rock, all of its rocks connected to each other, and has to fit the
+    // chamber when it appears.
+    pub fn from_str(input: &str, config: &Config) -> Result<ShapeFactory, ShapeError> {
+        let mut shapes: Vec<Shape> = vec![];
+        let mut names = vec![];
+
+        // Lines of the shape being read, along with their line numbers
+        let mut block: Vec<(usize, &str)> = vec![];
+
+        let lines = input
+            .lines()
+            .enumerate()
+            .map(|(i, line)| (i + 1, line.trim()));
+        for (line_number, line) in lines.chain([(0, "")]) {
+            if line.starts_with("//") {
+                continue;
+            }
+
+            if !line.is_empty() {
+                block.push((line_number, line));
+                continue;
+            }
+
+            if !block.is_empty() {
+                let (shape, name) = Shape::parse(&block, shapes.len() + 1, config)?;
+                shapes.push(shape);
+                names.push(name);
+                block.clear();
+            }
+        }
+
+        if shapes.is_empty() {
+            return Err(ShapeError {
+                index: 0,
+                name: None,
+                line_number: 0,
+                message: String::from("no shapes found"),
+            });
+        }
+
+        Ok(ShapeFactory {
+            shapes,
+            names,
+            current: 0,
+        })
+    }
+
+    fn produce(&mut self) -> Shape {
+        let current = self.shapes[self.current];
+
+        self.current += 1;
+        if self.current == self.shapes.len() {
+            self.current = 0;
+        }
+
+        current
+    }
+
+    fn name(&self, shape: &Shape) -> Option<&str> {
+        self.names[shape.index].as_deref()
+    }
+
+    fn produce_at(&mut self, (x, y): (usize, usize)) -> Shape {
+        let mut shape = self.produce();
+        for row in shape.rows.iter_mut() {
+            *row <<= x;
+        }
+        shape.y = y;
+        shape
+    }
+}
+
+impl Config {
+    pub fn new() -> Config {
+        Config {
+            width: 7,
+            spawn_x: 2,
+            spawn_y: 3,
+        }
+    }
+
+    // Applies the option at `args[i]`, returning how many arguments it took,
+    // or None if it's not a chamber option
+    pub fn parse_option(&mut self, args: &[String], i: usize) -> Option<usize> {
+        let value = args.get(i + 1);
+
+        match args[i].as_str() {
+            "--width" => self.width = parse_number(&args[i], value),
+            "--spawn-x" => self.spawn_x = parse_number(&args[i], value),
+            "--spawn-y" => self.spawn_y = parse_number(&args[i], value),
+            _ => return None,
+        }
+
+        if self.width == 0 || self.width > Row::BITS as usize {
+            panic!("The chamber must be between 1 and {} units wide", Row::BITS);
+        }
+
+        Some(2)
+    }
+}
+
+impl Tetris {
+    pub fn new(config: Config) -> Tetris {
+        Tetris {
+            max_x: config.width - 1,
+            max_y: 0,
+            rows: vec![],
+            pruned_rows: 0,
+            prune: true,
+            config,
+            falling_shape: None,
+        }
+    }
+
+    fn full_row(&self) -> Row {
+        ((1u32 << (self.max_x + 1)) - 1) as Row
+    }
+
+    fn row(&self, y: usize) -> Row {
+        if y < self.pruned_rows {
+            return self.full_row();
+        }
+
+        *self.rows.get(y - self.pruned_rows).unwrap_or(&0)
+    }
+
+    fn collides(&self, shape: &Shape) -> bool {
+        shape
+            .rows()
+            .iter()
+            .enumerate()
+            .any(|(i, row)| row & self.row(shape.y + i) != 0)
+    }
+
+    fn move_falling_shape_to(&mut self, direction: Direction) -> bool {
+        let moved_shape = match self.falling_shape {
+            Some(shape) => shape.moved_to(direction, self.max_x),
+            None => return false,
+        };
+
+        match moved_shape {
+            Some(shape) if !self.collides(&shape) => {
+                self.falling_shape = Some(shape);
+                true
+            }
+            _ => false,
+        }
+    }
+
+    fn move_falling_shape_down(&mut self) -> bool {
+        let mut falling_shape = match self.falling_shape.take() {
+            Some(shape) => shape,
+            None => return false,
+        };
+
+        let has_moved = if falling_shape.y > 0 {
+            falling_shape.y -= 1;
+            let collides = self.collides(&falling_shape);
+            if collides {
+                falling_shape.y += 1;
+            }
+            !collides
+        } else {
+            false
+        };
+
+        self.falling_shape = Some(falling_shape);
+        has_moved
+    }
+
+    pub fn round(&mut self, shape_factory: &mut ShapeFactory, movements: &mut Directions) {
+        self.spawn(shape_factory);
+
+        loop {
+            self.push(movements);
+            if !self.fall() {
+                break;
+            }
+        }
+    }
+
+    fn spawn(&mut self, shape_factory: &mut ShapeFactory) {
+        let point = (self.config.spawn_x, self.max_y + self.config.spawn_y);
+        self.falling_shape = Some(shape_factory.produce_at(point));
+    }
+
+    fn push(&mut self, movements: &mut Directions) -> (Direction, bool) {
+        let direction = movements.next();
+        (direction, self.move_falling_shape_to(direction))
+    }
+
+    // Returns false once the falling shape comes to rest
+    fn fall(&mut self) -> bool {
+        if self.move_falling_shape_down() {
+            return true;
+        }
+
+        self.move_falling_shape_to_resting_points();
+        false
+    }
+
+    fn move_falling_shape_to_resting_points(&mut self) -> bool {
+        let falling_shape = match self.falling_shape.take() {
+            Some(shape) => shape,
+            None => return false,
+        };
+
+        let top = falling_shape.y + falling_shape.height;
+        while self.pruned_rows + self.rows.len() < top {
+            self.rows.push(0);
+        }
+
+        let mut full_row = None;
+        for (i, row) in falling_shape.rows().iter().enumerate() {
+            let index = falling_shape.y + i - self.pruned_rows;
+            self.rows[index] |= row;
+
+            if self.rows[index] == self.full_row() {
+                full_row = Some(index);
+            }
+        }
+
+        if top > self.max_y {
+            self.max_y = top;
+        }
+
+        // Nothing can fall through a fully blocked row
+        if let (true, Some(index)) = (self.prune, full_row) {
+            self.rows.drain(..index);
+            self.pruned_rows += index;
+        }
+
+        true
+    }
+
+    // Rows from the top of the tower down to the deepest one that a falling
+    // rock could still get into, moving down and sideways through empty cells.
+    // The row blocking it is included as well, as rocks can rest on it. Rows
+    // below it can never affect the tower again.
+    //
+    // None while rocks can still get all the way down to the floor, or deeper
+    // than MAX_SURFACE_DEPTH (e.g. a wide chamber where the tower never
+    // reaches one of the walls).
+    #[allow(dead_code)] // Only used by part 2
+    pub fn surface(&self) -> Option<Vec<Row>> {
+        let full_row = self.full_row();
+        let mut surface = vec![];
+
+        // The row above the tower is completely empty
+        let mut reachable = full_row;
+        let mut y = self.max_y;
+
+        while reachable != 0 {
+            if y == 0 || surface.len() == MAX_SURFACE_DEPTH {
+                return None;
+            }
+            y -= 1;
+
+            let row = self.row(y);
+            surface.push(row);
+
+            let mut next = reachable & !row;
+            loop {
+                let spread = (next | (next << 1) | (next >> 1)) & !row & full_row;
+                if spread == next {
+                    break;
+                }
+                next = spread;
+            }
+
+            reachable = next;
+        }
+
+        Some(surface)
+    }
+
+    #[allow(dead_code)] // Only used by part 1
+    pub fn print(&self) {
+        self.print_rows(usize::MAX);
+    }
+
+    fn get_row(&self, y: usize) -> String {
+        let row = self.row(y);
+
+        (0..=self.max_x)
+            .map(|x| match &self.falling_shape {
+                Some(shape) if shape.covers(x, y) => '@',
+                _ if row & (1 << x) != 0 => '#',
+                _ => '.',
+            })
+            .collect()
+    }
+
+    // Prints up to `limit` rows from the top of the chamber
+    fn print_rows(&self, limit: usize) {
+        let y_margin = 5;
+        let top = self.max_y + y_margin;
+        let bottom = cmp::max(self.pruned_rows, (top + 1).saturating_sub(limit));
+
+        for reverse_y in bottom..=top {
+            let y = top - (reverse_y - bottom);
+
+            print!("{:01$} ", y, 6);
+            print!("|{}|", self.get_row(y));
+            println!();
+        }
+
+        if bottom > 0 {
+            print!("       ({} rows below)\n\n", bottom);
+            return;
+        }
+
+        print!("       +");
+        for _ in 0..=self.max_x {
+            print!("-");
+        }
+        print!("+\n\n");
+    }
+}
+
+impl Inspector {
+    pub fn new(tetris: Tetris, shape_factory: ShapeFactory, directions: Directions) -> Inspector {
+        let current = Snapshot {
+            tetris,
+            shape_factory,
+            directions,
+        };
+
+        Inspector {
+            snapshots: vec![current.clone()],
+            current,
+            rocks: 0,
+            next_step: Step::Push,
+        }
+    }
+
+    pub fn run(&mut self, rock: u64) {
+        self.go_to(rock);
+
+        let stdin = io::stdin();
+        for line in stdin.lock().lines() {
+            let line = line.unwrap();
+            let mut words = line.split_whitespace();
+
+            match words.next() {
+                None | Some("s") => self.step(),
+                Some("r") => {
+                    self.finish_rock();
+                    self.show();
+                }
+                Some("n") => self.go_to(self.current_rock() + 1),
+                Some("p") => self.go_to(self.current_rock().saturating_sub(1)),
+                Some("g") => match words.next().and_then(|rock| rock.parse().ok()) {
+                    Some(rock) => self.go_to(rock),
+                    None => println!("Usage: g <rock number>"),
+                },
+                Some("q") => break,
+                Some(_) => {
+                    println!("Commands:");
+                    println!("  s or enter   move the falling rock one step");
+                    println!("  r            let the falling rock come to rest");
+                    println!("  n            go to the next rock");
+                    println!("  p            go to the previous rock");
+                    println!("  g N          go to rock N");
+                    println!("  q            quit");
+                }
+            }
+        }
+    }
+
+    // Shows rock `rock` (starting at 1) as it appears at the top of the chamber
+    fn go_to(&mut self, rock: u64) {
+        let rocks = rock.max(1) - 1;
+        let falling = self.current.tetris.falling_shape.is_some();
+
+        if rocks < self.rocks || (rocks == self.rocks && falling) {
+            let snapshot = (rocks / SNAPSHOT_EVERY) as usize;
+            self.current = self.snapshots[snapshot].clone();
+            self.rocks = snapshot as u64 * SNAPSHOT_EVERY;
+        }
+
+        self.finish_rock();
+        while self.rocks < rocks {
+            self.current.tetris.round(
+                &mut self.current.shape_factory,
+                &mut self.current.directions,
+            );
+            self.rested();
+        }
+
+        self.spawn();
+    }
+
+    // Rock being shown: the one falling, or the last one to come to rest
+    fn current_rock(&self) -> u64 {
+        match self.current.tetris.falling_shape {
+            Some(_) => self.rocks + 1,
+            None => self.rocks,
+        }
+    }
+
+    fn spawn(&mut self) {
+        let current = &mut self.current;
+        current.tetris.spawn(&mut current.shape_factory);
+        self.next_step = Step::Push;
+
+        let shape = current.tetris.falling_shape.as_ref().unwrap();
+        match current.shape_factory.name(shape) {
+            Some(name) => println!("Rock {} ({name}) begins falling:", self.rocks + 1),
+            None => println!("Rock {} begins falling:", self.rocks + 1),
+        }
+        self.show();
+    }
+
+    fn step(&mut self) {
+        if self.current.tetris.falling_shape.is_none() {
+            self.spawn();
+            return;
+        }
+
+        let current = &mut self.current;
+
+        match self.next_step {
+            Step::Push => {
+                let (direction, moved) = current.tetris.push(&mut current.directions);
+                if moved {
+                    println!("Jet of gas pushes rock {}", direction);
+                } else {
+                    println!("Jet of gas pushes rock {}, but nothing happens", direction);
+                }
+                self.next_step = Step::Fall;
+            }
+            Step::Fall => {
+                if current.tetris.fall() {
+                    println!("Rock falls 1 unit");
+                } else {
+                    println!("Rock comes to rest");
+                    self.rested();
+                }
+                self.next_step = Step::Push;
+            }
+        }
+
+        self.show();
+    }
+
+    fn finish_rock(&mut self) {
+        let current = &mut self.current;
+        if current.tetris.falling_shape.is_none() {
+            return;
+        }
+
+        if self.next_step == Step::Fall && !current.tetris.fall() {
+            self.rested();
+            return;
+        }
+
+        loop {
+            current.tetris.push(&mut current.directions);
+            if !current.tetris.fall() {
+                break;
+            }
+        }
+
+        self.rested();
+    }
+
+    fn rested(&mut self) {
+        self.rocks += 1;
+        self.next_step = Step::Push;
+
+        if self.rocks.is_multiple_of(SNAPSHOT_EVERY)
+            && self.snapshots.len() as u64 == self.rocks / SNAPSHOT_EVERY
+        {
+            self.snapshots.push(self.current.clone());
+        }
+    }
+
+    fn show(&self) {
+        self.current.tetris.print_rows(INSPECT_ROWS);
+        println!(
+            "{} rocks at rest, tower height {}",
+            self.rocks, self.current.tetris.max_y
+        );
+    }
+}
+
+pub fn parse_number(option: &str, value: Option<&String>) -> usize {
+    value
+        .and_then(|value| value.parse().ok())
+        .unwrap_or_else(|| panic!("{option} expects a number"))
+}
+
+// Whether all rocks can be reached from any of them, moving up, down, left or right
+fn is_connected(rocks: &[(usize, usize)]) -> bool {
+    let mut visited = vec![false; rocks.len()];
+    let mut queue = vec![0];
+    visited[0] = true;
+
+    while let Some(i) = queue.pop() {
+        let (x, y) = rocks[i];
+
+        for (j, &(other_x, other_y)) in rocks.iter().enumerate() {
+            if !visited[j] && x.abs_diff(other_x) + y.abs_diff(other_y) == 1 {
+                visited[j] = true;
+                queue.push(j);
+            }
+        }
+    }
+
+    visited.iter().all(|&v| v)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PLUS: &str = "name: Plus\n.#.\n###\n.#.\n";
+
+    // Shapes are plain values, so moving one copies it without allocating
+    fn is_copy<T: Copy>(value: T) -> T {
+        value
+    }
+
+    #[test]
+    fn shifted_shapes_collide() {
+        let config = Config::new();
+        let mut shape_factory = ShapeFactory::from_str(PLUS, &config).unwrap();
+        let mut tetris = Tetris::new(config);
+
+        // A rock at x=5, next to the middle row of the plus
+        tetris.rows = vec![0, 0b0100000, 0];
+        let shape = is_copy(shape_factory.produce_at((2, 0)));
+        assert_eq!(shape.rows(), [0b0001000, 0b0011100, 0b0001000]);
+        assert!(!tetris.collides(&shape));
+
+        let right = shape.moved_to(Direction::Right, tetris.max_x).unwrap();
+        assert_eq!(right.rows(), [0b0010000, 0b0111000, 0b0010000]);
+        assert!(tetris.collides(&right));
+
+        let left = shape.moved_to(Direction::Left, tetris.max_x).unwrap();
+        assert!(!tetris.collides(&left));
+        assert_eq!(shape.rows(), [0b0001000, 0b0011100, 0b0001000]);
+
+        let edge = left.moved_to(Direction::Left, tetris.max_x).unwrap();
+        assert!(edge.moved_to(Direction::Left, tetris.max_x).is_none());
+        assert_eq!(shape_factory.name(&edge), Some("Plus"));
+    }
+
+    #[test]
+    fn rotated_shapes_keep_their_corner() {
+        let config = Config::new();
+        let mut shape_factory = ShapeFactory::from_str("####\n", &config).unwrap();
+        let shape = shape_factory.produce_at((2, 0));
+
+        let vertical = shape.moved_to(Direction::Clockwise, 6).unwrap();
+        assert_eq!(vertical.rows(), [0b100; 4]);
+        assert_eq!(shape_factory.name(&vertical), None);
+
+        let horizontal = vertical.moved_to(Direction::CounterClockwise, 6).unwrap();
+        assert_eq!(horizontal.rows(), shape.rows());
+    }
+
+    #[test]
+    fn shapes_too_tall() {
+        let input = "#\n".repeat(MAX_SHAPE_SIZE + 1);
+        let error = ShapeFactory::from_str(&input, &Config::new()).unwrap_err();
+        assert!(error.message.contains("17 units tall"), "{error}");
+    }
+}
@@ -3,18 +3,18 @@ use std::fs;
 
 #[derive(Debug, Hash, Eq, PartialEq)]
 struct Cube {
-    x: u32,
-    y: u32,
-    z: u32,
+    x: i32,
+    y: i32,
+    z: i32,
 }
 
 impl Cube {
-    fn new((x, y, z): (u32, u32, u32)) -> Cube {
+    fn new((x, y, z): (i32, i32, i32)) -> Cube {
         Cube { x, y, z }
     }
 
     fn from_str(input: &str) -> Cube {
-        let values: Vec<u32> = input.split(",").map(|val| val.parse().unwrap()).collect();
+        let values: Vec<i32> = input.split(",").map(|val| val.parse().unwrap()).collect();
         Cube {
             x: values[0],
             y: values[1],
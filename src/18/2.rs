@@ -1,75 +1,188 @@
+// Options:
+//   --pockets         list the air pockets trapped inside the droplet
+//   --obj FILE        write the surface of the droplet as an OBJ mesh
+//   --stl FILE        write the surface of the droplet as an ASCII STL mesh
+//   --faces KIND      faces included in the meshes:
+//                       exterior: only those touching water (default)
+//                       all:      every lava face not touching other lava
+//   --merge           merge adjacent coplanar faces into larger rectangles
+//   --bench SIZE      instead of reading input.txt, time the solution on a
+//                     generated droplet filling a SIZE^3 box
+
 use std::cmp;
 use std::collections::HashMap;
 use std::collections::HashSet;
 use std::collections::VecDeque;
+use std::env;
+use std::fmt;
 use std::fs;
+use std::process;
+use std::time::Instant;
+
+// Points in the box around the droplet, above which `Voxels` would take too
+// much memory (128 MiB for each set)
+const MAX_VOXELS: usize = 1 << 30;
+
+// The input has i32 coordinates. They are stored as i64 so that neighbours and
+// the box around the droplet never overflow.
+#[derive(Debug, Hash, Eq, PartialEq, Copy, Clone)]
+struct Point(i64, i64, i64); // x, y, z
+
+// Dense set of the points inside a box, with one bit per point. Points are
+// stored along x first, then y, then z.
+#[derive(Debug)]
+struct Voxels {
+    min: Point,
+    size: [usize; 3],
+    bits: Vec<u64>,
+}
 
+// Air trapped inside the droplet, which water can't reach
+#[derive(Debug)]
+struct Pocket {
+    voxels: usize,
+    min: Point,
+    max: Point,
+    // Faces of lava cubes touching the pocket
+    surface_area: usize,
+}
+
+// Square face of a lava cube, with side 1
 #[derive(Debug, Hash, Eq, PartialEq, Copy, Clone)]
-struct Point(i32, i32, i32); // x, y, z
+struct Face {
+    // Axis the face is perpendicular to (0 for x, 1 for y and 2 for z)
+    axis: usize,
+    // Whether the face looks towards the positive side of the axis
+    positive: bool,
+    // Corner of the face with the smallest coordinates
+    origin: Point,
+}
 
-#[derive(Debug, Eq, PartialEq)]
-enum Object {
-    Lava,
-    Water,
+// Rectangle made of one or more coplanar faces. `u` and `v` are the two other
+// axes after `axis`, in this order (y and z for x, z and x for y, x and y for
+// z), so that u × v points towards the positive side of `axis`.
+#[derive(Debug)]
+struct Quad {
+    axis: usize,
+    positive: bool,
+    plane: i64,
+    min_u: i64,
+    min_v: i64,
+    max_u: i64,
+    max_v: i64,
 }
 
+// Lava and water share the same box: the bounding box of the droplet, with one
+// more point on each side so that water can surround it
 #[derive(Debug)]
 struct Problem {
-    items: HashMap<Point, Object>,
-    max_x: i32,
-    max_y: i32,
-    max_z: i32,
+    lava: Voxels,
+    water: Voxels,
+    min: Point,
+    max: Point,
+}
+
+// The box around the droplet has more than MAX_VOXELS points
+#[derive(Debug)]
+struct TooLarge {
+    min: Point,
+    max: Point,
 }
 
 impl Problem {
-    fn new(lava_points: Vec<Point>) -> Problem {
-        let mut items = HashMap::new();
-        let (mut max_x, mut max_y, mut max_z) = (0, 0, 0);
-        for lava_point in lava_points {
-            let Point(x, y, z) = lava_point;
-            items.insert(lava_point, Object::Lava);
+    fn new(lava_points: Vec<Point>) -> Result<Problem, TooLarge> {
+        // Bounding box of the droplet. An empty droplet gets a box with a single
+        // point, which the water fills entirely.
+        let Point(mut min_x, mut min_y, mut min_z) =
+            lava_points.first().cloned().unwrap_or(Point(0, 0, 0));
+        let (mut max_x, mut max_y, mut max_z) = (min_x, min_y, min_z);
+
+        for &Point(x, y, z) in &lava_points {
+            min_x = cmp::min(x, min_x);
+            min_y = cmp::min(y, min_y);
+            min_z = cmp::min(z, min_z);
             max_x = cmp::max(x, max_x);
             max_y = cmp::max(y, max_y);
             max_z = cmp::max(z, max_z);
         }
-        Problem {
-            items,
-            max_x,
-            max_y,
-            max_z,
+
+        let (min, max) = (Point(min_x, min_y, min_z), Point(max_x, max_y, max_z));
+        let mut lava = Voxels::around(min, max).ok_or(TooLarge { min, max })?;
+        for lava_point in lava_points {
+            lava.insert(lava_point);
         }
+
+        Ok(Problem {
+            water: Voxels::around(min, max).ok_or(TooLarge { min, max })?,
+            lava,
+            min,
+            max,
+        })
     }
 
     // Flood fill on the exterior, aroundlava cubes:
     // https://en.wikipedia.org/wiki/Flood_fill
     // Later, we can count the faces of lava cubes that touch water,
-    // which will ignore faces not touching anything (air pockets)
+    // which will ignore faces not touching anything (air pockets).
+    // The fill starts at a corner just outside the bounding box, which can
+    // never be lava. Each seed fills a whole run of free points along x, and
+    // seeds the free runs of the four rows next to it (scanline fill).
     fn water_fill(&mut self) {
-        let mut queue = VecDeque::from([Point(0, 0, 0)]);
-        let mut queue_items = HashSet::<Point>::new(); // for O(1) duplicate lookup
+        let [size_x, size_y, size_z] = self.water.size;
+        let mut seeds = vec![(0, 0, 0)];
 
-        while let Some(point) = queue.pop_front() {
-            queue_items.remove(&point);
-            self.items.insert(point, Object::Water);
+        while let Some((x, y, z)) = seeds.pop() {
+            let row = (z * size_y + y) * size_x;
+            if !self.is_free(row + x) {
+                continue;
+            }
 
-            for adj in point.adjacent_points() {
-                if self.items.contains_key(&adj)
-                    || !self.is_valid_point(adj)
-                    || queue_items.contains(&adj)
-                {
+            let (mut start, mut end) = (x, x);
+            while start > 0 && self.is_free(row + start - 1) {
+                start -= 1;
+            }
+            while end + 1 < size_x && self.is_free(row + end + 1) {
+                end += 1;
+            }
+
+            for x in start..=end {
+                self.water.set(row + x);
+            }
+
+            let rows = [
+                (y.wrapping_sub(1), z),
+                (y + 1, z),
+                (y, z.wrapping_sub(1)),
+                (y, z + 1),
+            ];
+
+            for (y, z) in rows {
+                if y >= size_y || z >= size_z {
                     continue;
                 }
 
-                queue.push_back(adj);
-                queue_items.insert(adj);
+                let row = (z * size_y + y) * size_x;
+                let mut in_run = false;
+
+                for x in start..=end {
+                    let free = self.is_free(row + x);
+                    if free && !in_run {
+                        seeds.push((x, y, z));
+                    }
+                    in_run = free;
+                }
             }
         }
     }
 
+    fn is_free(&self, index: usize) -> bool {
+        !self.lava.get(index) && !self.water.get(index)
+    }
+
     fn total_lava_surface_area(&self) -> usize {
-        self.items
-            .iter()
-            .map(|(point, _)| self.lava_surface_area(point))
+        self.lava
+            .points()
+            .map(|point| self.lava_surface_area(&point))
             .sum()
     }
 
@@ -85,36 +198,337 @@ impl Problem {
             .count()
     }
 
-    fn has_lava_at(&self, point: &Point) -> bool {
-        if !self.items.contains_key(point) {
-            return false;
+    // Every point that is neither lava nor water after `water_fill` is air
+    // trapped inside the droplet. Groups these points into connected pockets.
+    fn air_pockets(&self) -> Vec<Pocket> {
+        let mut pockets = vec![];
+        let mut visited =
+            Voxels::around(self.min, self.max).expect("same box as the lava and water");
+
+        for z in self.min.2..=self.max.2 {
+            for y in self.min.1..=self.max.1 {
+                for x in self.min.0..=self.max.0 {
+                    let point = Point(x, y, z);
+                    if self.has_lava_at(&point)
+                        || self.has_water_at(&point)
+                        || visited.contains(&point)
+                    {
+                        continue;
+                    }
+
+                    pockets.push(self.air_pocket_at(point, &mut visited));
+                }
+            }
+        }
+
+        pockets
+    }
+
+    fn air_pocket_at(&self, start: Point, visited: &mut Voxels) -> Pocket {
+        let mut pocket = Pocket {
+            voxels: 0,
+            min: start,
+            max: start,
+            surface_area: 0,
+        };
+
+        let mut queue = VecDeque::from([start]);
+        visited.insert(start);
+
+        while let Some(point) = queue.pop_front() {
+            pocket.add(point);
+
+            for adj in point.adjacent_points() {
+                if self.has_lava_at(&adj) {
+                    pocket.surface_area += 1;
+                } else if !visited.contains(&adj) {
+                    visited.insert(adj);
+                    queue.push_back(adj);
+                }
+            }
         }
 
-        *self.items.get(point).unwrap() == Object::Lava
+        pocket
+    }
+
+    // Faces of lava cubes touching water, or touching anything but lava when
+    // `exterior_only` is false
+    fn lava_faces(&self, exterior_only: bool) -> Vec<Face> {
+        let mut faces = vec![];
+
+        for point in self.lava.points() {
+            for (i, adj) in point.adjacent_points().iter().enumerate() {
+                let visible = if exterior_only {
+                    self.has_water_at(adj)
+                } else {
+                    !self.has_lava_at(adj)
+                };
+
+                if !visible {
+                    continue;
+                }
+
+                // Same order as `adjacent_points`
+                let (axis, positive) = [
+                    (2, false),
+                    (2, true),
+                    (1, false),
+                    (1, true),
+                    (0, false),
+                    (0, true),
+                ][i];
+                let origin = if positive { *adj } else { point };
+
+                faces.push(Face {
+                    axis,
+                    positive,
+                    origin,
+                });
+            }
+        }
+
+        faces
+    }
+
+    fn has_lava_at(&self, point: &Point) -> bool {
+        self.lava.contains(point)
     }
 
     fn has_water_at(&self, point: &Point) -> bool {
-        if !self.items.contains_key(point) {
-            return false;
+        self.water.contains(point)
+    }
+}
+
+impl Voxels {
+    // Box from `min` to `max` with one more point on each side, or None if it
+    // has more than MAX_VOXELS points
+    fn around(min: Point, max: Point) -> Option<Voxels> {
+        let (min, max) = (min.coords(), max.coords());
+        let mut size = [0; 3];
+        let mut len: usize = 1;
+
+        for axis in 0..3 {
+            size[axis] = usize::try_from(max[axis] - min[axis] + 3).ok()?;
+            len = len.checked_mul(size[axis])?;
+        }
+
+        if len > MAX_VOXELS {
+            return None;
+        }
+
+        Some(Voxels {
+            min: Point(min[0] - 1, min[1] - 1, min[2] - 1),
+            size,
+            bits: vec![0; len.div_ceil(64)],
+        })
+    }
+
+    fn index(&self, point: &Point) -> Option<usize> {
+        let (coords, min) = (point.coords(), self.min.coords());
+        let mut index = 0;
+
+        for axis in (0..3).rev() {
+            let offset = coords[axis] - min[axis];
+            if offset < 0 || offset as usize >= self.size[axis] {
+                return None;
+            }
+
+            index = index * self.size[axis] + offset as usize;
+        }
+
+        Some(index)
+    }
+
+    fn point(&self, index: usize) -> Point {
+        let [size_x, size_y, _] = self.size;
+        let Point(min_x, min_y, min_z) = self.min;
+
+        Point(
+            min_x + (index % size_x) as i64,
+            min_y + (index / size_x % size_y) as i64,
+            min_z + (index / size_x / size_y) as i64,
+        )
+    }
+
+    fn get(&self, index: usize) -> bool {
+        self.bits[index / 64] & (1 << (index % 64)) != 0
+    }
+
+    fn set(&mut self, index: usize) {
+        self.bits[index / 64] |= 1 << (index % 64);
+    }
+
+    fn contains(&self, point: &Point) -> bool {
+        self.index(point).is_some_and(|index| self.get(index))
+    }
+
+    fn insert(&mut self, point: Point) {
+        let index = self.index(&point).expect("point outside of the box");
+        self.set(index);
+    }
+
+    fn len(&self) -> usize {
+        self.bits
+            .iter()
+            .map(|word| word.count_ones() as usize)
+            .sum()
+    }
+
+    fn points(&self) -> impl Iterator<Item = Point> + '_ {
+        self.bits
+            .iter()
+            .enumerate()
+            .filter(|(_, &word)| word != 0)
+            .flat_map(move |(i, &word)| {
+                (0..64)
+                    .filter(move |bit| word & (1 << bit) != 0)
+                    .map(move |bit| self.point(i * 64 + bit))
+            })
+    }
+}
+
+impl fmt::Display for TooLarge {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let Point(min_x, min_y, min_z) = self.min;
+        let Point(max_x, max_y, max_z) = self.max;
+
+        write!(
+            f,
+            "The droplet spans from {min_x},{min_y},{min_z} to {max_x},{max_y},{max_z}, \
+             a box with more than {MAX_VOXELS} points"
+        )
+    }
+}
+
+impl Pocket {
+    fn add(&mut self, point: Point) {
+        let Point(x, y, z) = point;
+        let Point(min_x, min_y, min_z) = self.min;
+        let Point(max_x, max_y, max_z) = self.max;
+
+        self.voxels += 1;
+        self.min = Point(cmp::min(x, min_x), cmp::min(y, min_y), cmp::min(z, min_z));
+        self.max = Point(cmp::max(x, max_x), cmp::max(y, max_y), cmp::max(z, max_z));
+    }
+}
+
+impl Face {
+    fn to_quad(self) -> Quad {
+        let coords = self.origin.coords();
+        let (u, v) = ((self.axis + 1) % 3, (self.axis + 2) % 3);
+
+        Quad {
+            axis: self.axis,
+            positive: self.positive,
+            plane: coords[self.axis],
+            min_u: coords[u],
+            min_v: coords[v],
+            max_u: coords[u] + 1,
+            max_v: coords[v] + 1,
+        }
+    }
+}
+
+impl Quad {
+    // Merges coplanar faces into as few rectangles as possible, growing each
+    // rectangle along u first and then along v while every face is there
+    fn merge(faces: &[Face]) -> Vec<Quad> {
+        let mut planes: HashMap<(usize, bool, i64), HashSet<(i64, i64)>> = HashMap::new();
+
+        for face in faces {
+            let quad = face.to_quad();
+            planes
+                .entry((quad.axis, quad.positive, quad.plane))
+                .or_default()
+                .insert((quad.min_u, quad.min_v));
         }
 
-        *self.items.get(point).unwrap() == Object::Water
+        let mut quads = vec![];
+
+        for ((axis, positive, plane), mut cells) in planes {
+            let mut sorted: Vec<(i64, i64)> = cells.iter().cloned().collect();
+            sorted.sort_by_key(|&(u, v)| (v, u));
+
+            for (min_u, min_v) in sorted {
+                if !cells.contains(&(min_u, min_v)) {
+                    continue;
+                }
+
+                let mut max_u = min_u + 1;
+                while cells.contains(&(max_u, min_v)) {
+                    max_u += 1;
+                }
+
+                let mut max_v = min_v + 1;
+                while (min_u..max_u).all(|u| cells.contains(&(u, max_v))) {
+                    max_v += 1;
+                }
+
+                for u in min_u..max_u {
+                    for v in min_v..max_v {
+                        cells.remove(&(u, v));
+                    }
+                }
+
+                quads.push(Quad {
+                    axis,
+                    positive,
+                    plane,
+                    min_u,
+                    min_v,
+                    max_u,
+                    max_v,
+                });
+            }
+        }
+
+        quads
     }
 
-    fn is_valid_point(&self, Point(x, y, z): Point) -> bool {
-        x >= -1
-            && y >= -1
-            && z >= -1
-            && x <= self.max_x + 1
-            && y <= self.max_y + 1
-            && z <= self.max_z + 1
+    fn normal(&self) -> [i32; 3] {
+        let mut normal = [0; 3];
+        normal[self.axis] = if self.positive { 1 } else { -1 };
+        normal
+    }
+
+    // Splits the quad in two triangles, with their vertices in counter-clockwise
+    // order when looking at them from outside the droplet
+    fn triangles(&self) -> [[[i64; 3]; 3]; 2] {
+        let corner = |u: i64, v: i64| {
+            let mut point = [0; 3];
+            point[self.axis] = self.plane;
+            point[(self.axis + 1) % 3] = u;
+            point[(self.axis + 2) % 3] = v;
+            point
+        };
+
+        let mut corners = [
+            corner(self.min_u, self.min_v),
+            corner(self.max_u, self.min_v),
+            corner(self.max_u, self.max_v),
+            corner(self.min_u, self.max_v),
+        ];
+
+        if !self.positive {
+            corners.reverse();
+        }
+
+        [
+            [corners[0], corners[1], corners[2]],
+            [corners[0], corners[2], corners[3]],
+        ]
     }
 }
 
 impl Point {
     fn from_str(input: &str) -> Point {
         let values: Vec<i32> = input.split(",").map(|val| val.parse().unwrap()).collect();
-        Point(values[0], values[1], values[2])
+        Point(values[0].into(), values[1].into(), values[2].into())
+    }
+
+    fn coords(&self) -> [i64; 3] {
+        let Point(x, y, z) = *self;
+        [x, y, z]
     }
 
     fn adjacent_points(&self) -> [Point; 6] {
@@ -132,11 +546,212 @@ impl Point {
 }
 
 fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+
+    if args.first().map(String::as_str) == Some("--bench") {
+        let size = args.get(1).and_then(|size| size.parse().ok());
+        bench(size.expect("--bench expects a size"));
+        return;
+    }
+
     let points = get_input_points();
-    let mut problem = Problem::new(points);
+    let mut problem = new_problem(points);
     problem.water_fill();
     let result = problem.total_lava_surface_area();
     println!("{result}");
+
+    let (mut pockets, mut merge, mut exterior_only) = (false, false, true);
+    let (mut obj, mut stl) = (None, None);
+
+    let mut i = 0;
+    while i < args.len() {
+        let value = args.get(i + 1).cloned();
+
+        match args[i].as_str() {
+            "--pockets" => pockets = true,
+            "--merge" => merge = true,
+            "--obj" => obj = Some(value.expect("--obj expects a file")),
+            "--stl" => stl = Some(value.expect("--stl expects a file")),
+            "--faces" => match value.as_deref() {
+                Some("exterior") => exterior_only = true,
+                Some("all") => exterior_only = false,
+                _ => panic!("--faces expects exterior or all"),
+            },
+            arg => panic!("Unknown option {arg}"),
+        }
+
+        i += match args[i].as_str() {
+            "--obj" | "--stl" | "--faces" => 2,
+            _ => 1,
+        };
+    }
+
+    if pockets {
+        print_air_pockets(&problem);
+    }
+
+    if obj.is_some() || stl.is_some() {
+        let faces = problem.lava_faces(exterior_only);
+        let quads: Vec<Quad> = if merge {
+            Quad::merge(&faces)
+        } else {
+            faces.iter().map(|face| face.to_quad()).collect()
+        };
+
+        if let Some(path) = obj {
+            fs::write(path, to_obj(&quads)).unwrap();
+        }
+        if let Some(path) = stl {
+            fs::write(path, to_stl(&quads)).unwrap();
+        }
+    }
+}
+
+fn to_obj(quads: &[Quad]) -> String {
+    let mut vertices: HashMap<[i64; 3], usize> = HashMap::new();
+    let mut output = String::new();
+    let mut faces = String::new();
+
+    for quad in quads {
+        for triangle in quad.triangles() {
+            let mut indexes = vec![];
+
+            for vertex in triangle {
+                let next_index = vertices.len() + 1;
+                let index = *vertices.entry(vertex).or_insert_with(|| {
+                    let [x, y, z] = vertex;
+                    output.push_str(&format!("v {x} {y} {z}\n"));
+                    next_index
+                });
+                indexes.push(index.to_string());
+            }
+
+            faces.push_str(&format!("f {}\n", indexes.join(" ")));
+        }
+    }
+
+    output + &faces
+}
+
+fn to_stl(quads: &[Quad]) -> String {
+    let mut output = String::from("solid droplet\n");
+
+    for quad in quads {
+        let [nx, ny, nz] = quad.normal();
+
+        for triangle in quad.triangles() {
+            output.push_str(&format!("  facet normal {nx} {ny} {nz}\n    outer loop\n"));
+            for [x, y, z] in triangle {
+                output.push_str(&format!("      vertex {x} {y} {z}\n"));
+            }
+            output.push_str("    endloop\n  endfacet\n");
+        }
+    }
+
+    output + "endsolid droplet\n"
+}
+
+// Hollow sphere of diameter `size` with a hole in its shell, so water gets
+// inside, and a hollow ball floating in its middle, trapping a big air pocket
+fn bench(size: i64) {
+    let timer = Instant::now();
+    let radius = size as f64 / 2.0;
+    let mut points = vec![];
+
+    for x in 0..size {
+        for y in 0..size {
+            for z in 0..size {
+                let (dx, dy, dz) = (
+                    x as f64 + 0.5 - radius,
+                    y as f64 + 0.5 - radius,
+                    z as f64 + 0.5 - radius,
+                );
+                let distance = (dx * dx + dy * dy + dz * dz).sqrt();
+                let shell = distance <= radius && distance > radius - 2.0;
+                let hole = dx.abs() < 2.0 && dy.abs() < 2.0 && dz > 0.0;
+                let ball = distance <= radius / 3.0 && distance > radius / 6.0;
+
+                if (shell && !hole) || ball {
+                    points.push(Point(x, y, z));
+                }
+            }
+        }
+    }
+
+    println!(
+        "Generated {} lava cubes in a {size}^3 box in {:.2?}",
+        points.len(),
+        timer.elapsed()
+    );
+
+    let timer = Instant::now();
+    let mut problem = new_problem(points);
+    println!("Built the voxels in {:.2?}", timer.elapsed());
+
+    let timer = Instant::now();
+    problem.water_fill();
+    println!(
+        "Filled {} points with water in {:.2?}",
+        problem.water.len(),
+        timer.elapsed()
+    );
+
+    let timer = Instant::now();
+    let exterior = problem.total_lava_surface_area();
+    println!(
+        "Exterior surface area {exterior} in {:.2?}",
+        timer.elapsed()
+    );
+
+    let timer = Instant::now();
+    let pockets = problem.air_pockets();
+    let interior: usize = pockets.iter().map(|pocket| pocket.surface_area).sum();
+    println!(
+        "Found {} air pockets with surface area {interior} in {:.2?}",
+        pockets.len(),
+        timer.elapsed()
+    );
+
+    // Every lava face not touching lava touches either water or air
+    let all = problem.lava_faces(false).len();
+    assert_eq!(all, exterior + interior, "surface areas don't add up");
+}
+
+fn new_problem(points: Vec<Point>) -> Problem {
+    match Problem::new(points) {
+        Ok(problem) => problem,
+        Err(error) => {
+            eprintln!("{error}");
+            process::exit(1);
+        }
+    }
+}
+
+fn print_air_pockets(problem: &Problem) {
+    let pockets = problem.air_pockets();
+    let lava = problem.lava.len();
+    let air: usize = pockets.iter().map(|pocket| pocket.voxels).sum();
+    let surface_area: usize = pockets.iter().map(|pocket| pocket.surface_area).sum();
+
+    println!("\n{} air pockets:", pockets.len());
+
+    for pocket in &pockets {
+        let Point(min_x, min_y, min_z) = pocket.min;
+        let Point(max_x, max_y, max_z) = pocket.max;
+
+        println!(
+            "  {} voxels from {},{},{} to {},{},{}, surface area {}",
+            pocket.voxels, min_x, min_y, min_z, max_x, max_y, max_z, pocket.surface_area
+        );
+    }
+
+    println!(
+        "\n{} of {} voxels of the droplet are air ({:.2}%), adding {} to its surface area",
+        air,
+        lava + air,
+        100.0 * air as f64 / cmp::max(lava + air, 1) as f64,
+        surface_area
+    );
 }
 
 fn get_input_points() -> Vec<Point> {
@@ -148,3 +763,39 @@ fn get_input_points() -> Vec<Point> {
         .map(|line| Point::from_str(line))
         .collect()
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EXAMPLE: &str = "2,2,2\n1,2,2\n3,2,2\n2,1,2\n2,3,2\n2,2,1\n2,2,3\n2,2,4\n2,2,6\n\
+                           1,2,5\n3,2,5\n2,1,5\n2,3,5";
+
+    fn exterior_surface_area(input: &str) -> Result<usize, TooLarge> {
+        let mut problem = Problem::new(input.lines().map(Point::from_str).collect())?;
+        problem.water_fill();
+        Ok(problem.total_lava_surface_area())
+    }
+
+    #[test]
+    fn example() {
+        assert_eq!(exterior_surface_area(EXAMPLE).unwrap(), 58);
+    }
+
+    #[test]
+    fn extreme_coordinates() {
+        let input = "-2147483648,2147483647,-2147483648\n-2147483647,2147483647,-2147483648";
+        assert_eq!(exterior_surface_area(input).unwrap(), 10);
+    }
+
+    #[test]
+    fn too_large() {
+        for input in [
+            "0,0,0\n-2147483648,0,0",
+            "0,0,0\n2000000000,0,0",
+            "0,0,0\n2000,2000,2000",
+        ] {
+            assert!(exterior_surface_area(input).is_err(), "{input}");
+        }
+    }
+}
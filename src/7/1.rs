@@ -2,154 +2,72 @@
 //! [dependencies]
 //! regex = "1"
 //! lazy_static = "1.4.0"
+//! serde_json = { version = "1", features = ["preserve_order"] }
 //! ```
 
-// This challenge taught me that Rust makes it hard to work with tree structures.
-// Decided to use a hash map approach instead.
+// Options:
+//   --tree      print the reconstructed filesystem as a tree, with sizes
+//   --du        list directories from largest to smallest, like `du -h`
+//   --top N     list the N largest files
+//   --bytes     print sizes in bytes instead of human-readable units
+//   --materialize [DIR]
+//               recreate the filesystem in DIR, or in a new directory in /tmp,
+//               with sparse files
+//   --from DIR  explore a real directory instead of reading input.txt
+//   --json FILE write the filesystem to FILE as JSON
+//   --from-json FILE
+//               load the filesystem from a JSON file instead
+//   --transcript DIR
+//               only print the terminal output of exploring DIR
+
+mod filesystem;
+
+use filesystem::{Report, Source};
+use std::env;
+use std::process;
 
-use lazy_static::lazy_static;
-use regex::Regex;
-use std::collections::HashMap;
-use std::fs;
-
-const ROOT: &str = "root";
-
-#[derive(Debug)]
-#[allow(dead_code)]
-enum Node {
-    File { name: String, size: i32 },
-    Dir { name: String },
-}
-
-#[derive(Debug)]
-struct FileSystem {
-    dirs: HashMap<String, Vec<Node>>,
-}
-
-impl FileSystem {
-    fn new() -> FileSystem {
-        let mut dirs: HashMap<String, Vec<Node>> = HashMap::new();
-        dirs.insert(String::from(ROOT), vec![]);
-        FileSystem { dirs }
-    }
-
-    fn add_dir(&mut self, parent_path: &Vec<String>, name: &str) {
-        let mut path = parent_path.clone();
-        path.push(name.to_string());
-
-        let path_key = FileSystem::path_to_key(&path);
-        let parent_path_key = FileSystem::path_to_key(&parent_path);
-
-        if self.dirs.contains_key(&path_key) {
-            return;
-        }
-
-        self.dirs.insert(path_key, vec![]);
-        self.dirs
-            .get_mut(&parent_path_key)
-            .unwrap()
-            .push(Node::Dir {
-                name: name.to_string(),
-            });
-    }
-
-    fn add_file(&mut self, parent_path: &Vec<String>, file: Node) {
-        let parent_path_key = FileSystem::path_to_key(&parent_path);
-        self.dirs.get_mut(&parent_path_key).unwrap().push(file);
-    }
-
-    fn path_to_key(path: &Vec<String>) -> String {
-        path.join("/")
-    }
-
-    fn get_dir_size(&self, path: &str) -> i32 {
-        let mut total = 0;
-        let children = self.dirs.get(path).unwrap();
-
-        for node in children {
-            let size = match node {
-                Node::File { size, .. } => *size,
-                Node::Dir { name, .. } => {
-                    let sub_path = path.to_string() + "/" + name;
-                    self.get_dir_size(&sub_path)
-                }
-            };
-
-            total += size;
-        }
-
-        total
-    }
-}
-
-fn read_input() -> String {
-    fs::read_to_string("input.txt").unwrap()
-}
-
-fn parse_terminal_output(terminal: String) -> FileSystem {
-    let mut fs = FileSystem::new();
-    let mut current_path: Vec<String> = vec![String::from(ROOT)];
-
-    for line in terminal.trim().lines() {
-        if line[0..4].eq("$ cd") {
-            cd(&mut fs, &mut current_path, &line);
-        } else if line[0..4].eq("dir ") {
-            let dir = &line[4..];
-            fs.add_dir(&current_path, dir);
-        } else {
-            add_file(&mut fs, &current_path, &line);
+fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+    let mut report = Report::new();
+    let mut source = Source::new();
+
+    let mut i = 0;
+    while i < args.len() {
+        match report
+            .parse_option(&args, i)
+            .or_else(|| source.parse_option(&args, i))
+        {
+            Some(taken) => i += taken,
+            None => panic!("Unknown option {}", args[i]),
         }
     }
 
-    fs
-}
-
-fn cd(fs: &mut FileSystem, current_path: &mut Vec<String>, command: &str) {
-    // skip "$ cd "
-    match &command[5..] {
-        "/" => {
-            current_path.drain(1..);
-        }
-
-        ".." => {
-            if current_path.len() > 1 {
-                current_path.pop();
+    if source.print {
+        match source.read() {
+            Ok(output) => print!("{output}"),
+            Err(error) => {
+                eprintln!("Could not read the terminal output: {error}");
+                process::exit(1);
             }
         }
-
-        dir_name => {
-            fs.add_dir(&current_path, dir_name);
-            current_path.push(String::from(dir_name));
-        }
-    }
-}
-
-fn add_file(fs: &mut FileSystem, current_path: &Vec<String>, line: &str) {
-    lazy_static! {
-        static ref RE: Regex = Regex::new(r"^(\d+) (.+)$").unwrap();
+        return;
     }
 
-    match RE.captures(line) {
-        Some(cap) => {
-            let size: i32 = cap[1].parse().unwrap();
-            let name = cap[2].to_string();
-            fs.add_file(current_path, Node::File { name, size });
+    let fs = match source.load() {
+        Ok(fs) => fs,
+        Err(error) => {
+            eprintln!("{error}");
+            process::exit(1);
         }
+    };
 
-        None => (),
-    }
-}
-
-fn main() {
-    let input = read_input();
-    let fs = parse_terminal_output(input);
-
-    let result: i32 = fs
-        .dirs
-        .iter()
-        .map(|(path, _)| fs.get_dir_size(path))
+    let result: u64 = fs
+        .dirs()
+        .map(|dir| fs.size(dir))
         .filter(|size| *size <= 100000)
         .sum();
 
     println!("{result}");
+
+    report.print(&fs);
 }
@@ -2,165 +2,407 @@
 //! [dependencies]
 //! regex = "1"
 //! lazy_static = "1.4.0"
+//! serde_json = { version = "1", features = ["preserve_order"] }
 //! ```
 
-// This challenge taught me that Rust makes it hard to work with tree structures.
-// Decided to use a hash map approach instead.
-
-use lazy_static::lazy_static;
-use regex::Regex;
+// Options:
+//   --disk N        total space of the disk (default 70000000)
+//   --required N    free space needed to run the update (default 30000000)
+//   --set           also find the directories, none inside another, that free
+//                   enough space while deleting as little as possible
+//   --tree          print the reconstructed filesystem as a tree, with sizes
+//   --du            list directories from largest to smallest, like `du -h`
+//   --top N         list the N largest files
+//   --bytes         print sizes in bytes instead of human-readable units
+//   --materialize [DIR]
+//                   recreate the filesystem in DIR, or in a new directory in
+//                   /tmp, with sparse files
+//   --from DIR      explore a real directory instead of reading input.txt
+//   --json FILE     write the filesystem to FILE as JSON
+//   --from-json FILE
+//                   load the filesystem from a JSON file instead
+//   --transcript DIR
+//                   only print the terminal output of exploring DIR
+
+mod filesystem;
+
+use filesystem::{FileSystem, Node, NodeId, Report, Source, ROOT};
 use std::collections::HashMap;
-use std::fs;
-
-const ROOT: &str = "root";
+use std::env;
+use std::ops::Range;
+use std::process;
 
+// Directories to delete, none of them inside another
 #[derive(Debug)]
-#[allow(dead_code)]
-enum Node {
-    File { name: String, size: i32 },
-    Dir { name: String },
+struct Plan {
+    dirs: Vec<NodeId>,
+    freed: u64,
 }
 
-#[derive(Debug)]
-struct FileSystem {
-    space: i32,
-    dirs: HashMap<String, Vec<Node>>,
-}
+// How much more space has to be freed, or an error if it's impossible
+fn space_needed(fs: &FileSystem, disk: u64, required: u64) -> Result<u64, String> {
+    let used = fs.size(ROOT);
 
-impl FileSystem {
-    fn new(space: i32) -> FileSystem {
-        let mut dirs: HashMap<String, Vec<Node>> = HashMap::new();
-        dirs.insert(String::from(ROOT), vec![]);
-        FileSystem { dirs, space }
+    if used > disk {
+        return Err(format!(
+            "The filesystem takes {used}, more than the {disk} of the disk"
+        ));
+    }
+    if required > disk {
+        return Err(format!(
+            "The update needs {required} free, more than the {disk} of the disk"
+        ));
     }
 
-    fn add_dir(&mut self, parent_path: &Vec<String>, name: &str) {
-        let mut path = parent_path.clone();
-        path.push(name.to_string());
-
-        let path_key = FileSystem::path_to_key(&path);
-        let parent_path_key = FileSystem::path_to_key(&parent_path);
+    Ok(required.saturating_sub(disk - used))
+}
 
-        if self.dirs.contains_key(&path_key) {
-            return;
-        }
+fn smallest_dir(fs: &FileSystem, needed: u64) -> NodeId {
+    // The root frees everything, which is always enough after `space_needed`
+    fs.dirs()
+        .filter(|&dir| fs.size(dir) >= needed)
+        .min_by_key(|&dir| fs.size(dir))
+        .unwrap()
+}
 
-        self.dirs.insert(path_key, vec![]);
-        self.dirs
-            .get_mut(&parent_path_key)
-            .unwrap()
-            .push(Node::Dir {
-                name: name.to_string(),
-            });
+// Dynamic programming over the directories in pre-order, where deleting a
+// directory skips all of its subdirectories. Only amounts smaller than what
+// `single` frees are worth tracking, and they are kept as sorted ranges, so the
+// memory used depends on the directory tree rather than on the sizes involved.
+// Each chosen directory is then found by going over the positions again, up to
+// the first one where deleting it completes the rest of the plan.
+fn smallest_set(fs: &FileSystem, needed: u64, single: NodeId) -> Plan {
+    if needed == 0 {
+        return Plan {
+            dirs: vec![],
+            freed: 0,
+        };
     }
 
-    fn add_file(&mut self, parent_path: &Vec<String>, file: Node) {
-        let parent_path_key = FileSystem::path_to_key(&parent_path);
-        self.dirs.get_mut(&parent_path_key).unwrap().push(file);
-    }
+    let (mut dirs, mut ends) = (vec![], vec![]);
+    pre_order_dirs(fs, ROOT, &mut dirs, &mut ends);
+
+    let bound = fs.size(single);
+    let reachable = reachable_amounts(fs, &dirs, &ends, needed, bound, |_, _| false);
+    let i = reachable.partition_point(|range| range.end <= needed);
+    let Some(freed) = reachable.get(i).map(|range| range.start.max(needed)) else {
+        return Plan {
+            dirs: vec![single],
+            freed: bound,
+        };
+    };
+
+    let mut plan = Plan {
+        dirs: vec![],
+        freed,
+    };
+    let (mut amount, mut position) = (freed, dirs.len());
+
+    while amount > 0 {
+        let mut chosen = None;
+        reachable_amounts(fs, &dirs, &ends, freed, freed + 1, |i, reachable| {
+            let size = fs.size(dirs[i]);
+            if ends[i] <= position && size <= amount && contains(reachable, amount - size) {
+                chosen = Some(i);
+            }
+            chosen.is_some() || i + 1 == position
+        });
 
-    fn path_to_key(path: &Vec<String>) -> String {
-        path.join("/")
+        let i = chosen.unwrap();
+        plan.dirs.push(dirs[i]);
+        amount -= fs.size(dirs[i]);
+        position = i;
     }
 
-    fn get_dir_size(&self, path: &str) -> i32 {
-        let mut total = 0;
-        let children = self.dirs.get(path).unwrap();
+    plan.dirs.reverse();
+    plan
+}
 
-        for node in children {
-            let size = match node {
-                Node::File { size, .. } => *size,
-                Node::Dir { name, .. } => {
-                    let sub_path = path.to_string() + "/" + name;
-                    self.get_dir_size(&sub_path)
-                }
-            };
+// The amounts below `bound` that deleting the directories before each position
+// can free, passed to `visit` until it returns true. Nothing larger than the
+// smallest amount reaching `needed` is worth tracking either.
+fn reachable_amounts(
+    fs: &FileSystem,
+    dirs: &[NodeId],
+    ends: &[usize],
+    needed: u64,
+    mut bound: u64,
+    mut visit: impl FnMut(usize, &[Range<u64>]) -> bool,
+) -> Vec<Range<u64>> {
+    // Deleting nothing always frees nothing
+    let mut reachable = vec![Range { start: 0, end: 1 }];
+    let mut pending: HashMap<usize, Vec<Range<u64>>> = HashMap::new();
+
+    for (position, (&dir, &end)) in dirs.iter().zip(ends).enumerate() {
+        if let Some(amounts) = pending.remove(&position) {
+            reachable = union(&reachable, &amounts);
+        }
+        if visit(position, &reachable) {
+            break;
+        }
+
+        let size = fs.size(dir);
+        let mut shifted = vec![];
+        for range in &reachable {
+            if range.start + size >= bound {
+                break;
+            }
 
-            total += size;
+            shifted.push(range.start + size..(range.end + size).min(bound));
+            if range.end + size > needed {
+                bound = (range.start + size).max(needed) + 1;
+                shifted.last_mut().unwrap().end = bound;
+                break;
+            }
         }
 
-        total
+        let target = pending.entry(end).or_default();
+        *target = union(target, &shifted);
     }
 
-    fn space_occupied(&self) -> i32 {
-        self.get_dir_size(ROOT)
+    if let Some(amounts) = pending.remove(&dirs.len()) {
+        reachable = union(&reachable, &amounts);
     }
+    reachable
+}
+
+// Lists the directories under `dir`, and for each one the position right after
+// its last subdirectory
+fn pre_order_dirs(fs: &FileSystem, dir: NodeId, dirs: &mut Vec<NodeId>, ends: &mut Vec<usize>) {
+    let position = dirs.len();
+    dirs.push(dir);
+    ends.push(0);
 
-    fn space_remaining(&self) -> i32 {
-        self.space - self.space_occupied()
+    for &child in fs.children(dir) {
+        if let Node::Dir { .. } = fs.node(child) {
+            pre_order_dirs(fs, child, dirs, ends);
+        }
     }
+
+    ends[position] = dirs.len();
 }
 
-fn read_input() -> String {
-    fs::read_to_string("input.txt").unwrap()
+fn contains(ranges: &[Range<u64>], amount: u64) -> bool {
+    let i = ranges.partition_point(|range| range.end <= amount);
+    ranges.get(i).is_some_and(|range| range.start <= amount)
 }
 
-fn parse_terminal_output(fs: &mut FileSystem, terminal: String) {
-    let mut current_path: Vec<String> = vec![String::from(ROOT)];
-
-    for line in terminal.trim().lines() {
-        if line[0..4].eq("$ cd") {
-            cd(fs, &mut current_path, &line);
-        } else if line[0..4].eq("dir ") {
-            let dir = &line[4..];
-            fs.add_dir(&current_path, dir);
-        } else {
-            add_file(fs, &current_path, &line);
+// Merges two sorted lists of ranges, joining the ones that touch
+fn union(a: &[Range<u64>], b: &[Range<u64>]) -> Vec<Range<u64>> {
+    let mut merged: Vec<Range<u64>> = Vec::with_capacity(a.len() + b.len());
+    let (mut a, mut b) = (a.iter().peekable(), b.iter().peekable());
+
+    loop {
+        let next = match (a.peek(), b.peek()) {
+            (Some(x), Some(y)) if x.start <= y.start => a.next(),
+            (Some(_), None) => a.next(),
+            _ => b.next(),
+        };
+        let Some(range) = next else {
+            break;
+        };
+
+        match merged.last_mut() {
+            Some(last) if range.start <= last.end => last.end = last.end.max(range.end),
+            _ => merged.push(range.clone()),
         }
     }
+
+    merged
 }
 
-fn cd(fs: &mut FileSystem, current_path: &mut Vec<String>, command: &str) {
-    // skip "$ cd "
-    match &command[5..] {
-        "/" => {
-            current_path.drain(1..);
+fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+    let mut report = Report::new();
+    let mut source = Source::new();
+    let (mut disk, mut required) = (70000000, 30000000);
+    let mut set = false;
+
+    let mut i = 0;
+    while i < args.len() {
+        let taken = report
+            .parse_option(&args, i)
+            .or_else(|| source.parse_option(&args, i));
+        if let Some(taken) = taken {
+            i += taken;
+            continue;
         }
 
-        ".." => {
-            if current_path.len() > 1 {
-                current_path.pop();
+        let value = args.get(i + 1).and_then(|value| value.parse().ok());
+        i += match args[i].as_str() {
+            "--disk" => {
+                disk = value.expect("--disk expects a size");
+                2
+            }
+            "--required" => {
+                required = value.expect("--required expects a size");
+                2
             }
+            "--set" => {
+                set = true;
+                1
+            }
+            arg => panic!("Unknown option {arg}"),
+        };
+    }
+
+    if source.print {
+        match source.read() {
+            Ok(output) => print!("{output}"),
+            Err(error) => {
+                eprintln!("Could not read the terminal output: {error}");
+                process::exit(1);
+            }
+        }
+        return;
+    }
+
+    let fs = match source.load() {
+        Ok(fs) => fs,
+        Err(error) => {
+            eprintln!("{error}");
+            process::exit(1);
         }
+    };
+
+    let needed = match space_needed(&fs, disk, required) {
+        Ok(needed) => needed,
+        Err(error) => {
+            eprintln!("{error}");
+            process::exit(1);
+        }
+    };
+
+    let dir = smallest_dir(&fs, needed);
+    let result = fs.size(dir);
 
-        dir_name => {
-            fs.add_dir(&current_path, dir_name);
-            current_path.push(String::from(dir_name));
+    println!("{result}");
+
+    if set {
+        let plan = smallest_set(&fs, needed, dir);
+
+        println!(
+            "\nDirectories to delete, freeing {} of the {} needed:",
+            report.format_size(plan.freed),
+            report.format_size(needed)
+        );
+
+        for dir in plan.dirs {
+            println!("{:>8}  {}", report.format_size(fs.size(dir)), fs.path(dir));
         }
     }
+
+    report.print(&fs);
 }
 
-fn add_file(fs: &mut FileSystem, current_path: &Vec<String>, line: &str) {
-    lazy_static! {
-        static ref RE: Regex = Regex::new(r"^(\d+) (.+)$").unwrap();
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Sizes far too large to index anything by byte
+    fn large_filesystem() -> (FileSystem, [NodeId; 3]) {
+        let mut fs = FileSystem::new();
+        let dirs = [
+            ("a", 6_000_000_000),
+            ("b", 5_000_000_000),
+            ("c", 4_000_000_000),
+        ]
+        .map(|(name, size)| {
+            let dir = fs.add_dir(ROOT, name);
+            fs.add_file(dir, "data", size);
+            dir
+        });
+        fs.compute_sizes();
+        (fs, dirs)
     }
 
-    match RE.captures(line) {
-        Some(cap) => {
-            let size: i32 = cap[1].parse().unwrap();
-            let name = cap[2].to_string();
-            fs.add_file(current_path, Node::File { name, size });
-        }
+    #[test]
+    fn set_with_large_sizes() {
+        let (fs, [_, b, c]) = large_filesystem();
+        let needed = space_needed(&fs, 20_000_000_000, 14_000_000_000).unwrap();
+        let plan = smallest_set(&fs, needed, smallest_dir(&fs, needed));
 
-        None => (),
+        assert_eq!(needed, 9_000_000_000);
+        assert_eq!(plan.dirs, vec![b, c]);
+        assert_eq!(plan.freed, 9_000_000_000);
     }
-}
 
-fn main() {
-    let input = read_input();
+    #[test]
+    fn set_falls_back_to_single_dir() {
+        let (fs, [_, b, _]) = large_filesystem();
+        let needed = space_needed(&fs, 20_000_000_000, 9_500_000_000).unwrap();
+        let plan = smallest_set(&fs, needed, smallest_dir(&fs, needed));
 
-    let mut fs = FileSystem::new(70000000);
-    parse_terminal_output(&mut fs, input);
+        assert_eq!(plan.dirs, vec![b]);
+        assert_eq!(plan.freed, 5_000_000_000);
+    }
 
-    let space_remaining = fs.space_remaining();
+    // Whether `dir` is somewhere below `other`
+    fn is_inside(fs: &FileSystem, dir: NodeId, other: NodeId) -> bool {
+        let mut node = dir;
+        while let Some(parent) = fs.parent(node) {
+            if parent == other {
+                return true;
+            }
+            node = parent;
+        }
+        false
+    }
 
-    let result: i32 = fs
-        .dirs
-        .iter()
-        .map(|(path, _)| fs.get_dir_size(path))
-        .filter(|size| (space_remaining + *size) > 30000000)
-        .min()
-        .unwrap();
+    // Compares the plan against trying every set of directories, on small
+    // random trees with nested directories
+    #[test]
+    fn set_matches_exhaustive_search() {
+        let mut seed: u64 = 12345;
+        let mut random = |n: usize| {
+            seed = seed
+                .wrapping_mul(6364136223846793005)
+                .wrapping_add(1442695040888963407);
+            (seed >> 33) as usize % n
+        };
+
+        for _ in 0..1000 {
+            let mut fs = FileSystem::new();
+            let mut dirs = vec![ROOT];
+            for i in 0..random(10) + 1 {
+                let parent = dirs[random(dirs.len())];
+                dirs.push(fs.add_dir(parent, &format!("d{i}")));
+            }
+            for i in 0..random(12) + 1 {
+                let parent = dirs[random(dirs.len())];
+                fs.add_file(parent, &format!("f{i}"), random(60) as u64 + 1);
+            }
+            fs.compute_sizes();
+
+            let needed = random(fs.size(ROOT) as usize) as u64 + 1;
+            let plan = smallest_set(&fs, needed, smallest_dir(&fs, needed));
+
+            let mut best = None;
+            for set in 0..1 << dirs.len() {
+                let chosen: Vec<NodeId> = (0..dirs.len())
+                    .filter(|i| set & (1 << i) != 0)
+                    .map(|i| dirs[i])
+                    .collect();
+                let nested = chosen
+                    .iter()
+                    .any(|&dir| chosen.iter().any(|&other| is_inside(&fs, dir, other)));
+                let freed: u64 = chosen.iter().map(|&dir| fs.size(dir)).sum();
+
+                if !nested && freed >= needed && best.is_none_or(|best| freed < best) {
+                    best = Some(freed);
+                }
+            }
 
-    println!("{result}");
+            let freed: u64 = plan.dirs.iter().map(|&dir| fs.size(dir)).sum();
+            assert_eq!(Some(plan.freed), best, "needed {needed} in {fs:?}");
+            assert_eq!(freed, plan.freed, "planned {:?} in {fs:?}", plan.dirs);
+            for &dir in &plan.dirs {
+                assert!(
+                    plan.dirs.iter().all(|&other| !is_inside(&fs, dir, other)),
+                    "planned {:?} in {fs:?}",
+                    plan.dirs
+                );
+            }
+        }
+    }
 }
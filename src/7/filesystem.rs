@@ -0,0 +1,888 @@
+// Directory tree reconstructed from the terminal output. Nodes are kept in an
+// arena and refer to each other by index, which avoids fighting the borrow
+// checker with parent links. The root is always the first node.
+
+use lazy_static::lazy_static;
+use regex::Regex;
+use serde_json::json;
+use serde_json::Value;
+use std::cmp::Reverse;
+use std::collections::HashMap;
+use std::env;
+use std::fmt;
+use std::fs::{create_dir, create_dir_all, read_dir, read_to_string, write, File};
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process;
+
+pub type NodeId = usize;
+
+pub const ROOT: NodeId = 0;
+
+#[derive(Debug)]
+pub enum Node {
+    File { name: String, size: u64 },
+    Dir { name: String, children: Vec<NodeId> },
+}
+
+// Line of `ls` output
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+enum Entry {
+    Dir(String),
+    File(String, u64),
+}
+
+#[derive(Debug)]
+pub struct ParseError {
+    pub line_number: usize,
+    pub line: String,
+    pub message: String,
+}
+
+// Where the terminal output comes from
+#[derive(Debug)]
+pub struct Source {
+    // Real directory to explore instead of reading input.txt
+    pub dir: Option<PathBuf>,
+    // Only print the terminal output of exploring `dir`
+    pub print: bool,
+    // JSON document to load instead of any terminal output
+    pub json: Option<PathBuf>,
+}
+
+// Reports printed after the result, see the options of each part
+#[derive(Debug)]
+pub struct Report {
+    pub tree: bool,
+    pub du: bool,
+    pub top: Option<usize>,
+    pub bytes: bool,
+    pub materialize: Option<PathBuf>,
+    pub json: Option<PathBuf>,
+}
+
+#[derive(Debug)]
+pub struct FileSystem {
+    nodes: Vec<Node>,
+    parents: Vec<Option<NodeId>>,
+    // Cumulative size of every node, filled in by `compute_sizes`
+    sizes: Vec<u64>,
+}
+
+impl Node {
+    pub fn name(&self) -> &str {
+        match self {
+            Node::File { name, .. } | Node::Dir { name, .. } => name,
+        }
+    }
+}
+
+impl FileSystem {
+    pub fn new() -> FileSystem {
+        FileSystem {
+            nodes: vec![Node::Dir {
+                name: String::from("/"),
+                children: vec![],
+            }],
+            parents: vec![None],
+            sizes: vec![],
+        }
+    }
+
+    pub fn node(&self, id: NodeId) -> &Node {
+        &self.nodes[id]
+    }
+
+    pub fn parent(&self, id: NodeId) -> Option<NodeId> {
+        self.parents[id]
+    }
+
+    pub fn children(&self, id: NodeId) -> &[NodeId] {
+        match &self.nodes[id] {
+            Node::Dir { children, .. } => children,
+            Node::File { .. } => &[],
+        }
+    }
+
+    pub fn child(&self, dir: NodeId, name: &str) -> Option<NodeId> {
+        self.children(dir)
+            .iter()
+            .copied()
+            .find(|&child| self.nodes[child].name() == name)
+    }
+
+    // Finds a node by the names of the directories leading to it from the root
+    #[cfg(test)]
+    pub fn lookup(&self, path: &[&str]) -> Option<NodeId> {
+        path.iter()
+            .try_fold(ROOT, |node, name| self.child(node, name))
+    }
+
+    // Absolute path of a node, such as /a/e
+    pub fn path(&self, id: NodeId) -> String {
+        let mut names = vec![];
+        let mut node = id;
+
+        while let Some(parent) = self.parents[node] {
+            names.push(self.nodes[node].name());
+            node = parent;
+        }
+
+        names.reverse();
+        format!("/{}", names.join("/"))
+    }
+
+    pub fn files(&self) -> impl Iterator<Item = NodeId> + '_ {
+        (0..self.nodes.len()).filter(|&id| matches!(self.nodes[id], Node::File { .. }))
+    }
+
+    pub fn dirs(&self) -> impl Iterator<Item = NodeId> + '_ {
+        (0..self.nodes.len()).filter(|&id| matches!(self.nodes[id], Node::Dir { .. }))
+    }
+
+    // Returns the existing directory if there is one with the same name
+    pub fn add_dir(&mut self, parent: NodeId, name: &str) -> NodeId {
+        if let Some(dir) = self.child(parent, name) {
+            return dir;
+        }
+
+        self.add_node(
+            parent,
+            Node::Dir {
+                name: name.to_string(),
+                children: vec![],
+            },
+        )
+    }
+
+    pub fn add_file(&mut self, parent: NodeId, name: &str, size: u64) -> NodeId {
+        self.add_node(
+            parent,
+            Node::File {
+                name: name.to_string(),
+                size,
+            },
+        )
+    }
+
+    fn add_node(&mut self, parent: NodeId, node: Node) -> NodeId {
+        let id = self.nodes.len();
+        self.nodes.push(node);
+        self.parents.push(Some(parent));
+
+        match &mut self.nodes[parent] {
+            Node::Dir { children, .. } => children.push(id),
+            Node::File { .. } => panic!("{} is not a directory", self.nodes[parent].name()),
+        }
+
+        id
+    }
+
+    // Sums the sizes of every directory in a single post-order traversal, so
+    // each node is only visited once
+    pub fn compute_sizes(&mut self) {
+        let mut sizes = vec![0; self.nodes.len()];
+        let mut stack = vec![(ROOT, false)];
+
+        while let Some((id, children_done)) = stack.pop() {
+            match &self.nodes[id] {
+                Node::File { size, .. } => sizes[id] = *size,
+                Node::Dir { children, .. } if children_done => {
+                    sizes[id] = children.iter().map(|&child| sizes[child]).sum();
+                }
+                Node::Dir { children, .. } => {
+                    stack.push((id, true));
+                    stack.extend(children.iter().map(|&child| (child, false)));
+                }
+            }
+        }
+
+        self.sizes = sizes;
+    }
+
+    // Nested document where every node has a name and a size, which is the
+    // cumulative size for directories, and directories have their children:
+    // {"name": "/", "size": 584, "children": [{"name": "i", "size": 584}]}
+    pub fn to_json(&self, id: NodeId) -> Value {
+        match &self.nodes[id] {
+            Node::File { name, size } => json!({ "name": name, "size": size }),
+            Node::Dir { name, children } => {
+                let children: Vec<Value> =
+                    children.iter().map(|&child| self.to_json(child)).collect();
+                json!({ "name": name, "size": self.size(id), "children": children })
+            }
+        }
+    }
+
+    // Loads a document written by `to_json`. Sizes of directories are optional,
+    // but must add up when present.
+    pub fn from_json(input: &str) -> Result<FileSystem, String> {
+        let json: Value =
+            serde_json::from_str(input).map_err(|error| format!("Invalid JSON: {error}"))?;
+        if !json["children"].is_array() {
+            return Err(String::from("The root must be a directory"));
+        }
+
+        let mut fs = FileSystem::new();
+        let mut dir_sizes = vec![];
+        fs.add_json_children(ROOT, &json, &mut dir_sizes)?;
+        fs.compute_sizes();
+
+        for (id, size) in dir_sizes {
+            if fs.size(id) != size {
+                return Err(format!(
+                    "{} has a size of {size}, but its contents add up to {}",
+                    fs.path(id),
+                    fs.size(id)
+                ));
+            }
+        }
+
+        Ok(fs)
+    }
+
+    fn add_json_children(
+        &mut self,
+        dir: NodeId,
+        json: &Value,
+        dir_sizes: &mut Vec<(NodeId, u64)>,
+    ) -> Result<(), String> {
+        if let Some(size) = json["size"].as_u64() {
+            dir_sizes.push((dir, size));
+        }
+
+        for child in json["children"].as_array().into_iter().flatten() {
+            let shown_name = match &child["name"] {
+                Value::String(name) => name.clone(),
+                other => other.to_string(),
+            };
+            let path = format!("{}/{shown_name}", self.path(dir).trim_end_matches('/'));
+            let name = match child["name"].as_str() {
+                Some(name) if is_valid_name(name) => name,
+                _ => return Err(format!("{path} does not have a valid name")),
+            };
+
+            if self.child(dir, name).is_some() {
+                return Err(format!("{path} appears twice"));
+            }
+
+            if child["children"].is_array() {
+                let id = self.add_dir(dir, name);
+                self.add_json_children(id, child, dir_sizes)?;
+            } else {
+                let size = child["size"]
+                    .as_u64()
+                    .ok_or(format!("{path} does not have a size"))?;
+                self.add_file(dir, name, size);
+            }
+        }
+
+        Ok(())
+    }
+
+    // Recreates the filesystem inside `dir`, which must not exist yet. Files
+    // are sparse: they have the right size but take almost no space on disk.
+    pub fn materialize(&self, dir: &Path) -> io::Result<()> {
+        if let Some(parent) = dir.parent() {
+            create_dir_all(parent)?;
+        }
+        create_dir(dir)?;
+
+        self.materialize_children(ROOT, dir)
+    }
+
+    fn materialize_children(&self, id: NodeId, dir: &Path) -> io::Result<()> {
+        for &child in self.children(id) {
+            let path = dir.join(self.nodes[child].name());
+
+            match &self.nodes[child] {
+                Node::Dir { .. } => {
+                    create_dir(&path)?;
+                    self.materialize_children(child, &path)?;
+                }
+                Node::File { size, .. } => File::create(&path)?.set_len(*size)?,
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn size(&self, id: NodeId) -> u64 {
+        assert_eq!(
+            self.sizes.len(),
+            self.nodes.len(),
+            "sizes are outdated, call compute_sizes first"
+        );
+
+        self.sizes[id]
+    }
+}
+
+impl Source {
+    pub fn new() -> Source {
+        Source {
+            dir: None,
+            print: false,
+            json: None,
+        }
+    }
+
+    // Applies the option at `args[i]`, returning how many arguments it took,
+    // or None if it's not a source option
+    pub fn parse_option(&mut self, args: &[String], i: usize) -> Option<usize> {
+        match args[i].as_str() {
+            "--from" | "--transcript" => {
+                let dir = args.get(i + 1).expect("expected a directory");
+                self.dir = Some(PathBuf::from(dir));
+                self.print = args[i] == "--transcript";
+                Some(2)
+            }
+            "--from-json" => {
+                let file = args.get(i + 1).expect("--from-json expects a file");
+                self.json = Some(PathBuf::from(file));
+                Some(2)
+            }
+            _ => None,
+        }
+    }
+
+    pub fn load(&self) -> Result<FileSystem, String> {
+        if let Some(file) = &self.json {
+            let input = read_to_string(file)
+                .map_err(|error| format!("Could not read {}: {error}", file.display()))?;
+            return FileSystem::from_json(&input);
+        }
+
+        let input = self
+            .read()
+            .map_err(|error| format!("Could not read the terminal output: {error}"))?;
+        parse_terminal_output(&input).map_err(|error| error.to_string())
+    }
+
+    pub fn read(&self) -> io::Result<String> {
+        match &self.dir {
+            Some(dir) => transcript(dir),
+            None => read_to_string("input.txt"),
+        }
+    }
+}
+
+impl Report {
+    pub fn new() -> Report {
+        Report {
+            tree: false,
+            du: false,
+            top: None,
+            bytes: false,
+            materialize: None,
+            json: None,
+        }
+    }
+
+    // Applies the option at `args[i]`, returning how many arguments it took,
+    // or None if it's not a report option
+    pub fn parse_option(&mut self, args: &[String], i: usize) -> Option<usize> {
+        match args[i].as_str() {
+            "--tree" => self.tree = true,
+            "--du" => self.du = true,
+            "--bytes" => self.bytes = true,
+            "--top" => {
+                let count = args.get(i + 1).and_then(|count| count.parse().ok());
+                self.top = Some(count.expect("--top expects a number of files"));
+                return Some(2);
+            }
+            "--json" => {
+                let file = args.get(i + 1).expect("--json expects a file");
+                self.json = Some(PathBuf::from(file));
+                return Some(2);
+            }
+            "--materialize" => {
+                // The directory is optional, a new one is made in /tmp if missing
+                return match args.get(i + 1).filter(|dir| !dir.starts_with("--")) {
+                    Some(dir) => {
+                        self.materialize = Some(PathBuf::from(dir));
+                        Some(2)
+                    }
+                    None => {
+                        let name = format!("day7-{}", process::id());
+                        self.materialize = Some(env::temp_dir().join(name));
+                        Some(1)
+                    }
+                };
+            }
+            _ => return None,
+        }
+
+        Some(1)
+    }
+
+    pub fn print(&self, fs: &FileSystem) {
+        if self.tree {
+            println!();
+            self.print_tree(fs, ROOT, "", "");
+        }
+
+        if self.du {
+            println!();
+            self.print_sorted(fs, fs.dirs());
+        }
+
+        if let Some(count) = self.top {
+            println!();
+            let files: Vec<NodeId> = fs.files().collect();
+            let mut files = self.sorted(fs, files.into_iter());
+            files.truncate(count);
+            self.print_sorted(fs, files.into_iter());
+        }
+
+        if let Some(file) = &self.json {
+            let json = serde_json::to_string_pretty(&fs.to_json(ROOT)).unwrap();
+            if let Err(error) = write(file, json) {
+                eprintln!("Could not write {}: {error}", file.display());
+                process::exit(1);
+            }
+        }
+
+        if let Some(dir) = &self.materialize {
+            if let Err(error) = fs.materialize(dir) {
+                eprintln!(
+                    "Could not write the filesystem to {}: {error}",
+                    dir.display()
+                );
+                process::exit(1);
+            }
+
+            println!("\nWrote the filesystem to {}", dir.display());
+        }
+    }
+
+    // Like `tree`, with entries sorted by name and the size of each one
+    fn print_tree(&self, fs: &FileSystem, id: NodeId, prefix: &str, children_prefix: &str) {
+        let node = fs.node(id);
+        let suffix = if let Node::Dir { .. } = node { "/" } else { "" };
+        let name = if id == ROOT { "" } else { node.name() };
+
+        println!("{prefix}{name}{suffix} ({})", self.format_size(fs.size(id)));
+
+        let mut children = fs.children(id).to_vec();
+        children.sort_by_key(|&child| fs.node(child).name());
+
+        for (i, &child) in children.iter().enumerate() {
+            let (prefix, next_prefix) = if i + 1 == children.len() {
+                ("└── ", "    ")
+            } else {
+                ("├── ", "│   ")
+            };
+
+            self.print_tree(
+                fs,
+                child,
+                &format!("{children_prefix}{prefix}"),
+                &format!("{children_prefix}{next_prefix}"),
+            );
+        }
+    }
+
+    // Like `du -ah | sort -hr`, one node per line with its size and path
+    fn print_sorted(&self, fs: &FileSystem, ids: impl Iterator<Item = NodeId>) {
+        for id in self.sorted(fs, ids) {
+            println!("{:>8}  {}", self.format_size(fs.size(id)), fs.path(id));
+        }
+    }
+
+    // Largest first, then by path
+    fn sorted(&self, fs: &FileSystem, ids: impl Iterator<Item = NodeId>) -> Vec<NodeId> {
+        let mut ids: Vec<NodeId> = ids.collect();
+        ids.sort_by_key(|&id| (Reverse(fs.size(id)), fs.path(id)));
+        ids
+    }
+
+    pub fn format_size(&self, size: u64) -> String {
+        if self.bytes {
+            size.to_string()
+        } else {
+            human_size(size)
+        }
+    }
+}
+
+// Size with binary units, the way `du -h` prints them: 584B, 8.0K, 24M
+fn human_size(size: u64) -> String {
+    const UNITS: [&str; 6] = ["B", "K", "M", "G", "T", "P"];
+
+    let mut value = size as f64;
+    let mut unit = 0;
+    while value >= 1024.0 && unit + 1 < UNITS.len() {
+        value /= 1024.0;
+        unit += 1;
+    }
+
+    if unit == 0 {
+        format!("{size}B")
+    } else if value < 10.0 {
+        format!("{value:.1}{}", UNITS[unit])
+    } else {
+        format!("{value:.0}{}", UNITS[unit])
+    }
+}
+
+impl Entry {
+    fn parse(line: &str) -> Option<Entry> {
+        lazy_static! {
+            static ref DIR: Regex = Regex::new(r"^dir (.+)$").unwrap();
+            static ref FILE: Regex = Regex::new(r"^(\d+) (.+)$").unwrap();
+        }
+
+        if let Some(cap) = DIR.captures(line) {
+            return Some(Entry::Dir(cap[1].to_string()));
+        }
+
+        let cap = FILE.captures(line)?;
+        let size = cap[1].parse().ok()?;
+        Some(Entry::File(cap[2].to_string(), size))
+    }
+
+    // Files of a real directory, sorted by name. Symbolic links and names that
+    // can't be written in the terminal output are left out.
+    fn read_dir(dir: &Path) -> io::Result<Vec<Entry>> {
+        let mut entries = vec![];
+
+        for dir_entry in read_dir(dir)? {
+            let dir_entry = dir_entry?;
+            let file_type = dir_entry.file_type()?;
+            let Ok(name) = dir_entry.file_name().into_string() else {
+                continue;
+            };
+
+            if !is_valid_name(&name) {
+                continue;
+            }
+
+            if file_type.is_dir() {
+                entries.push(Entry::Dir(name));
+            } else if file_type.is_file() {
+                entries.push(Entry::File(name, dir_entry.metadata()?.len()));
+            }
+        }
+
+        entries.sort_by(|a, b| a.name().cmp(b.name()));
+        Ok(entries)
+    }
+
+    fn name(&self) -> &str {
+        match self {
+            Entry::Dir(name) | Entry::File(name, _) => name,
+        }
+    }
+}
+
+impl fmt::Display for Entry {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Entry::Dir(name) => write!(f, "dir {name}"),
+            Entry::File(name, size) => write!(f, "{size} {name}"),
+        }
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(
+            f,
+            "Invalid terminal output on line {}: {}",
+            self.line_number, self.message
+        )?;
+        write!(f, "  {}", self.line)
+    }
+}
+
+// Names of files and directories can't be used to leave their directory
+fn is_valid_name(name: &str) -> bool {
+    !name.is_empty() && name != "." && name != ".." && !name.contains('/') && !name.contains('\n')
+}
+
+// Terminal output of exploring a real directory with `cd` and `ls`, in the
+// same format as the puzzle input
+pub fn transcript(dir: &Path) -> io::Result<String> {
+    let mut output = String::from("$ cd /\n");
+    write_transcript(dir, &mut output)?;
+    Ok(output)
+}
+
+fn write_transcript(dir: &Path, output: &mut String) -> io::Result<()> {
+    let entries = Entry::read_dir(dir)?;
+
+    output.push_str("$ ls\n");
+    for entry in &entries {
+        output.push_str(&format!("{entry}\n"));
+    }
+
+    for entry in &entries {
+        if let Entry::Dir(name) = entry {
+            output.push_str(&format!("$ cd {name}\n"));
+            write_transcript(&dir.join(name), output)?;
+            output.push_str("$ cd ..\n");
+        }
+    }
+
+    Ok(())
+}
+
+// Reads the commands and their output. Every `ls` adds the entries listed
+// below it to the current directory; listing a directory again is fine as
+// long as the output is the same, and is not counted twice.
+pub fn parse_terminal_output(terminal: &str) -> Result<FileSystem, ParseError> {
+    let mut fs = FileSystem::new();
+    let mut current_dir = ROOT;
+    // Line number and entries of the first listing of each directory
+    let mut listings: HashMap<NodeId, (usize, Vec<Entry>)> = HashMap::new();
+
+    let lines: Vec<(usize, &str)> = terminal
+        .lines()
+        .enumerate()
+        .map(|(i, line)| (i + 1, line.trim_end()))
+        .filter(|(_, line)| !line.is_empty())
+        .collect();
+
+    let mut i = 0;
+    while i < lines.len() {
+        let (line_number, line) = lines[i];
+        let error = |message: String| ParseError {
+            line_number,
+            line: line.to_string(),
+            message,
+        };
+        i += 1;
+
+        let Some(command) = line.strip_prefix("$ ") else {
+            return Err(error(String::from("expected a command")));
+        };
+
+        if let Some(target) = command.strip_prefix("cd ") {
+            current_dir = cd(&fs, current_dir, target).map_err(error)?;
+            continue;
+        }
+
+        if command != "ls" {
+            return Err(error(format!("unknown command `{command}`")));
+        }
+
+        let mut entries: Vec<Entry> = vec![];
+        while let Some(&(output_line_number, output)) = lines.get(i) {
+            if output.starts_with('$') {
+                break;
+            }
+            i += 1;
+
+            let error = |message: String| ParseError {
+                line_number: output_line_number,
+                line: output.to_string(),
+                message,
+            };
+            let entry = Entry::parse(output)
+                .ok_or_else(|| error(String::from("expected `dir NAME` or `SIZE NAME`")))?;
+
+            if !is_valid_name(entry.name()) {
+                return Err(error(format!("{} is not a valid name", entry.name())));
+            }
+            if entries.iter().any(|other| other.name() == entry.name()) {
+                return Err(error(format!("{} is listed twice", entry.name())));
+            }
+            entries.push(entry);
+        }
+
+        entries.sort();
+        if let Some((first_line_number, first_entries)) = listings.get(&current_dir) {
+            if *first_entries != entries {
+                return Err(error(format!(
+                    "{} lists different contents than on line {first_line_number}",
+                    fs.path(current_dir)
+                )));
+            }
+            continue;
+        }
+
+        for entry in &entries {
+            match entry {
+                Entry::Dir(name) => fs.add_dir(current_dir, name),
+                Entry::File(name, size) => fs.add_file(current_dir, name, *size),
+            };
+        }
+        listings.insert(current_dir, (line_number, entries));
+    }
+
+    fs.compute_sizes();
+    Ok(fs)
+}
+
+// Directories have to be listed before moving into them
+fn cd(fs: &FileSystem, current_dir: NodeId, target: &str) -> Result<NodeId, String> {
+    match target {
+        "/" => Ok(ROOT),
+        ".." => Ok(fs.parent(current_dir).unwrap_or(ROOT)),
+        dir_name => {
+            let Some(child) = fs.child(current_dir, dir_name) else {
+                return Err(format!(
+                    "no directory named {dir_name} in {}",
+                    fs.path(current_dir)
+                ));
+            };
+
+            match fs.node(child) {
+                Node::Dir { .. } => Ok(child),
+                Node::File { .. } => Err(format!("{dir_name} is a file")),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs::remove_dir_all;
+
+    const EXAMPLE: &str = "$ cd /
+$ ls
+dir a
+14848514 b.txt
+8504156 c.dat
+dir d
+$ cd a
+$ ls
+dir e
+29116 f
+2557 g
+62596 h.lst
+$ cd e
+$ ls
+584 i
+$ cd ..
+$ cd ..
+$ cd d
+$ ls
+4060174 j
+8033020 d.log
+5626152 d.ext
+7214296 k
+";
+
+    // Line number and message of the error in `terminal`
+    fn parse_error(terminal: &str) -> (usize, String) {
+        let error = parse_terminal_output(terminal).unwrap_err();
+        (error.line_number, error.message)
+    }
+
+    #[test]
+    fn parse_errors() {
+        let cases = [
+            ("$ cd /\n$ ls\ndir a\n5 a\n", 4, "a is listed twice"),
+            (
+                "$ cd /\n$ ls\n5 a\n$ ls\n6 a\n",
+                4,
+                "/ lists different contents than on line 2",
+            ),
+            ("$ cd /\n$ cd a\n", 2, "no directory named a in /"),
+            ("$ cd /\n$ ls\n5 a\n$ cd a\n", 4, "a is a file"),
+            ("$ cd /\n$ rm a\n", 2, "unknown command `rm a`"),
+            ("5 a\n$ cd /\n", 1, "expected a command"),
+            // Blank lines still count
+            (
+                "$ cd /\n\n$ ls\ndir a\n\n$ cd b\n",
+                6,
+                "no directory named b in /",
+            ),
+        ];
+
+        for (terminal, line_number, message) in cases {
+            assert_eq!(
+                parse_error(terminal),
+                (line_number, String::from(message)),
+                "{terminal:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn relisting_the_same_contents() {
+        let terminal = "$ cd /\n$ ls\ndir a\n5 b\n$ ls\n5 b\ndir a\n";
+        let fs = parse_terminal_output(terminal).unwrap();
+        assert_eq!(fs.size(ROOT), 5);
+    }
+
+    #[test]
+    fn json_round_trip() {
+        let fs = parse_terminal_output(EXAMPLE).unwrap();
+        let json = fs.to_json(ROOT).to_string();
+        let copy = FileSystem::from_json(&json).unwrap();
+
+        assert_eq!(copy.to_json(ROOT).to_string(), json);
+        assert_eq!(copy.size(ROOT), 48381165);
+        assert_eq!(copy.lookup(&["a", "e"]).map(|id| copy.size(id)), Some(584));
+    }
+
+    #[test]
+    fn json_errors() {
+        let cases = [
+            (
+                r#"{"name": "/", "size": 10, "children": [{"name": "a", "size": 5}]}"#,
+                "/ has a size of 10, but its contents add up to 5",
+            ),
+            (
+                r#"{"name": "/", "children": [{"name": "d", "size": 1, "children": [{"name": "a", "size": 2}]}]}"#,
+                "/d has a size of 1, but its contents add up to 2",
+            ),
+            (
+                r#"{"name": "/", "children": [{"name": "a", "size": 1}, {"name": "a", "children": []}]}"#,
+                "/a appears twice",
+            ),
+            (
+                r#"{"name": "/", "children": [{"name": "..", "size": 1}]}"#,
+                "/.. does not have a valid name",
+            ),
+            (
+                r#"{"name": "/", "children": [{"name": 7, "size": 1}]}"#,
+                "/7 does not have a valid name",
+            ),
+            (
+                r#"{"name": "/", "children": [{"name": "a"}]}"#,
+                "/a does not have a size",
+            ),
+            (r#"{"name": "/"}"#, "The root must be a directory"),
+        ];
+
+        for (input, message) in cases {
+            assert_eq!(
+                FileSystem::from_json(input).unwrap_err(),
+                message,
+                "{input}"
+            );
+        }
+        assert!(FileSystem::from_json("{")
+            .unwrap_err()
+            .starts_with("Invalid JSON"));
+    }
+
+    #[test]
+    fn materialize_and_read_back() {
+        let fs = parse_terminal_output(EXAMPLE).unwrap();
+        let dir = env::temp_dir().join(format!("day7-test-{}", process::id()));
+
+        fs.materialize(&dir).unwrap();
+        let copy = transcript(&dir).map(|output| parse_terminal_output(&output));
+        remove_dir_all(&dir).unwrap();
+
+        // Exploring the copy must give back the same filesystem
+        let copy = copy.unwrap().unwrap();
+        assert_eq!(copy.dirs().count(), fs.dirs().count(), "directories differ");
+        assert_eq!(copy.files().count(), fs.files().count(), "files differ");
+
+        for id in fs.dirs().chain(fs.files()) {
+            let path = fs.path(id);
+            let names: Vec<&str> = path.split('/').filter(|name| !name.is_empty()).collect();
+            let copied = copy
+                .lookup(&names)
+                .unwrap_or_else(|| panic!("{path} is missing"));
+            assert_eq!(copy.size(copied), fs.size(id), "size of {path}");
+        }
+    }
+}
@@ -1,87 +1,43 @@
-use std::collections::HashMap;
-use std::fs;
-
-#[derive(Debug)]
-struct TreeMap {
-    map: HashMap<(u32, u32), u32>,
-    size_x: u32,
-    size_y: u32,
-}
-
-impl TreeMap {
-    fn new() -> TreeMap {
-        TreeMap {
-            map: HashMap::new(),
-            size_x: 0,
-            size_y: 0,
-        }
-    }
-
-    fn from_str(input: &str) -> TreeMap {
-        let mut tree_map = TreeMap::new();
-        let mut x = 0;
-        let mut y = 0;
-
-        for line in input.trim().lines() {
-            x = 0;
-
-            for item in line.chars() {
-                let height = item.to_digit(10).unwrap();
-                tree_map.map.insert((x as u32, y as u32), height);
-                x += 1;
-            }
-
-            y += 1;
-        }
-
-        tree_map.size_x = x as u32;
-        tree_map.size_y = y as u32;
-        tree_map
-    }
+// Options:
+//   --verify            check the results against a slower approach
+//   --heights KIND      how heights are written: digits (default), letters from
+//                       a to z, or numbers separated by spaces or commas
+//   --visible           print the forest, with the trees visible from outside
+//                       in green
+//   --heatmap           print the scenic score of every tree as a heatmap, with
+//                       the best tree in cyan
+//   --visible-ppm FILE  write the forest with visible trees as a PPM image
+//   --heatmap-ppm FILE  write the scenic score heatmap as a PPM image
+//   --csv FILE          write the height, visibility and scenic score of every
+//                       tree as CSV
+//   --observer X,Y,H    count the trees seen by an observer of height H standing
+//                       at X,Y
+//   --sight KIND        directions the observers can look in: axes (default),
+//                       compass (axes and diagonals) or any line through a tree
+//   --visible-from X,Y,TX,TY
+//                       tell whether the tree at TX,TY can be seen from the top
+//                       of the tree at X,Y
+
+mod tree_map;
 
-    fn count_trees_visible(&self) -> i32 {
-        (0..self.size_x)
-            .map(|x| {
-                (0..self.size_y)
-                    .filter(|y| self.is_tree_visible((x, *y)))
-                    .count() as i32
-            })
-            .sum()
-    }
-
-    fn is_tree_visible(&self, pos: (u32, u32)) -> bool {
-        self.is_tree_visible_horizontally(pos) || self.is_tree_visible_vertically(pos)
-    }
-
-    fn is_tree_visible_horizontally(&self, (x, y): (u32, u32)) -> bool {
-        if x == 0 || x == self.size_x - 1 {
-            return true;
-        }
-
-        let tree_height = self.get((x, y));
-        let any_taller_before = (0..x).any(|xx| self.get((xx, y)) >= tree_height);
-        let any_taller_after = (x + 1..self.size_x).any(|xx| self.get((xx, y)) >= tree_height);
-        !(any_taller_before && any_taller_after)
-    }
+use std::fs;
+use std::process;
+use tree_map::{Options, TreeMap};
 
-    fn is_tree_visible_vertically(&self, (x, y): (u32, u32)) -> bool {
-        if y == 0 || y == self.size_y - 1 {
-            return true;
+fn main() {
+    let input = fs::read_to_string("input.txt").unwrap();
+    let options = Options::from_args();
+    let tree_map = match TreeMap::from_str(&input, options.decoder) {
+        Ok(tree_map) => tree_map,
+        Err(error) => {
+            eprintln!("{error}");
+            process::exit(1);
         }
+    };
+    let visible = tree_map.visibility();
+    let scores = tree_map.scenic_scores();
 
-        let tree_height = self.get((x, y));
-        let any_taller_before = (0..y).any(|yy| self.get((x, yy)) >= tree_height);
-        let any_taller_after = (y + 1..self.size_y).any(|yy| self.get((x, yy)) >= tree_height);
-        !(any_taller_before && any_taller_after)
-    }
-
-    fn get(&self, (x, y): (u32, u32)) -> u32 {
-        *self.map.get(&(x, y)).unwrap_or(&0)
-    }
-}
+    println!("{}", visible.iter().filter(|&&visible| visible).count());
 
-fn main() {
-    let input = fs::read_to_string("input.txt").unwrap();
-    let tree_map = TreeMap::from_str(&input);
-    println!("{}", tree_map.count_trees_visible());
+    options.output(&tree_map, &visible, &scores);
 }
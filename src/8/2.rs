@@ -1,114 +1,43 @@
-use std::collections::HashMap;
-use std::fs;
-use std::ops::Range;
-use std::ops::RangeInclusive;
-
-#[derive(Debug)]
-struct TreeMap {
-    map: HashMap<(u32, u32), u32>,
-    size_x: u32,
-    size_y: u32,
-}
-
-impl TreeMap {
-    fn new() -> TreeMap {
-        TreeMap {
-            map: HashMap::new(),
-            size_x: 0,
-            size_y: 0,
-        }
-    }
-
-    fn from_str(input: &str) -> TreeMap {
-        let mut tree_map = TreeMap::new();
-        let mut x = 0;
-        let mut y = 0;
-
-        for line in input.trim().lines() {
-            x = 0;
-
-            for item in line.chars() {
-                let height = item.to_digit(10).unwrap();
-                tree_map.map.insert((x as u32, y as u32), height);
-                x += 1;
-            }
-
-            y += 1;
-        }
-
-        tree_map.size_x = x as u32;
-        tree_map.size_y = y as u32;
-        tree_map
-    }
-
-    fn is_edge_tree(&self, (x, y): (u32, u32)) -> bool {
-        x == 0 || x == self.size_x - 1 || y == 0 || y == self.size_y - 1
-    }
-
-    fn get_scenic_score(&self, (x, y): (u32, u32)) -> i32 {
-        if self.is_edge_tree((x, y)) {
-            return 0;
-        }
-
-        let directions: Vec<Vec<(u32, u32)>> = vec![
-            gen_positions_reverse(y - 1..=0, |y| (x, y)),  // up
-            gen_positions(y + 1..self.size_y, |y| (x, y)), // down
-            gen_positions_reverse(x - 1..=0, |x| (x, y)),  // left
-            gen_positions(x + 1..self.size_x, |x| (x, y)), // right
-        ];
-
-        let mut score = 1;
-        let tree_height = self.get((x, y));
+// Options:
+//   --verify            check the results against a slower approach
+//   --heights KIND      how heights are written: digits (default), letters from
+//                       a to z, or numbers separated by spaces or commas
+//   --visible           print the forest, with the trees visible from outside
+//                       in green
+//   --heatmap           print the scenic score of every tree as a heatmap, with
+//                       the best tree in cyan
+//   --visible-ppm FILE  write the forest with visible trees as a PPM image
+//   --heatmap-ppm FILE  write the scenic score heatmap as a PPM image
+//   --csv FILE          write the height, visibility and scenic score of every
+//                       tree as CSV
+//   --observer X,Y,H    count the trees seen by an observer of height H standing
+//                       at X,Y
+//   --sight KIND        directions the observers can look in: axes (default),
+//                       compass (axes and diagonals) or any line through a tree
+//   --visible-from X,Y,TX,TY
+//                       tell whether the tree at TX,TY can be seen from the top
+//                       of the tree at X,Y
+
+mod tree_map;
 
-        for direction in directions {
-            let mut count = 0;
-
-            for pos in direction {
-                count += 1;
-                if self.get(pos) >= tree_height {
-                    break;
-                }
-            }
-
-            score *= count;
-        }
-
-        score
-    }
-
-    fn count_highest_scenic_score(&self) -> i32 {
-        let mut highest = 0;
+use std::fs;
+use std::process;
+use tree_map::{Options, TreeMap};
 
-        for x in 0..self.size_x {
-            for y in 0..self.size_y {
-                let score = self.get_scenic_score((x, y));
-                if score > highest {
-                    highest = score;
-                }
-            }
+fn main() {
+    let input = fs::read_to_string("input.txt").unwrap();
+    let options = Options::from_args();
+    let tree_map = match TreeMap::from_str(&input, options.decoder) {
+        Ok(tree_map) => tree_map,
+        Err(error) => {
+            eprintln!("{error}");
+            process::exit(1);
         }
+    };
+    let visible = tree_map.visibility();
+    let scores = tree_map.scenic_scores();
 
-        highest
-    }
+    println!("{}", scores.iter().max().unwrap_or(&0));
 
-    fn get(&self, (x, y): (u32, u32)) -> u32 {
-        *self.map.get(&(x, y)).unwrap_or(&0)
-    }
-}
-
-fn gen_positions_reverse<F: Fn(u32) -> (u32, u32)>(
-    range: RangeInclusive<u32>,
-    fun: F,
-) -> Vec<(u32, u32)> {
-    (*range.end()..=*range.start()).rev().map(fun).collect()
-}
-
-fn gen_positions<F: Fn(u32) -> (u32, u32)>(range: Range<u32>, fun: F) -> Vec<(u32, u32)> {
-    range.map(fun).collect()
-}
-
-fn main() {
-    let input = fs::read_to_string("input.txt").unwrap();
-    let tree_map = TreeMap::from_str(&input);
-    println!("{}", tree_map.count_highest_scenic_score());
+    options.output(&tree_map, &visible, &scores);
 }
@@ -0,0 +1,871 @@
+use std::env;
+use std::fmt;
+use std::fs;
+use std::ops::Range;
+use std::ops::RangeInclusive;
+
+// Side of the square drawn for each tree in PPM images
+const PPM_SCALE: usize = 6;
+const BEST_TREE_COLOR: [u8; 3] = [0, 220, 255];
+
+// Heights are stored row by row in a single vector, and printed back the way
+// they were written in the input
+#[derive(Debug)]
+pub struct TreeMap {
+    heights: Vec<u32>,
+    decoder: Decoder,
+    pub size_x: u32,
+    pub size_y: u32,
+}
+
+// Directions an observer can look in. Lines of sight go from tree to tree,
+// so looking along a slope of 1/2 sees the trees at (2, 1), (4, 2) and so on.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Sight {
+    // Up, down, left and right, as in the puzzle
+    Axes,
+    // The axes and the diagonals
+    Compass,
+    // Any line going through another tree
+    Any,
+}
+
+// How the heights of the trees are written in the input
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Decoder {
+    // One digit per tree, as in the puzzle: 30373
+    Digits,
+    // One letter per tree, from a (0) to z (25): dagcb
+    Letters,
+    // Numbers separated by spaces or commas: 12 0 130 7
+    Numbers,
+}
+
+#[derive(Debug)]
+pub struct ParseError {
+    pub line_number: usize,
+    pub line: String,
+    pub message: String,
+}
+
+#[derive(Debug)]
+pub struct Options {
+    pub verify: bool,
+    pub show_visible: bool,
+    pub show_heatmap: bool,
+    pub visible_ppm: Option<String>,
+    pub heatmap_ppm: Option<String>,
+    pub csv: Option<String>,
+    pub observer: Option<((u32, u32), u32)>,
+    // Observer and target trees
+    pub visible_from: Option<((u32, u32), (u32, u32))>,
+    pub sight: Sight,
+    pub decoder: Decoder,
+}
+
+impl TreeMap {
+    fn new(decoder: Decoder) -> TreeMap {
+        TreeMap {
+            heights: vec![],
+            decoder,
+            size_x: 0,
+            size_y: 0,
+        }
+    }
+
+    // Every line is a row of the forest, and all of them must have the same
+    // number of trees
+    pub fn from_str(input: &str, decoder: Decoder) -> Result<TreeMap, ParseError> {
+        let mut tree_map = TreeMap::new(decoder);
+
+        for (i, line) in input.trim_end().lines().enumerate() {
+            let error = |message: String| ParseError {
+                line_number: i + 1,
+                line: line.to_string(),
+                message,
+            };
+
+            let row = decoder.decode(line).map_err(error)?;
+            if row.is_empty() {
+                return Err(error(String::from("the row has no trees")));
+            }
+            if tree_map.size_y > 0 && row.len() != tree_map.size_x as usize {
+                return Err(error(format!(
+                    "the row has {} trees, but the first one has {}",
+                    row.len(),
+                    tree_map.size_x
+                )));
+            }
+
+            tree_map.size_x = row.len() as u32;
+            tree_map.size_y += 1;
+            tree_map.heights.extend(row);
+        }
+
+        if tree_map.heights.is_empty() {
+            return Err(ParseError {
+                line_number: 1,
+                line: String::new(),
+                message: String::from("the forest has no trees"),
+            });
+        }
+
+        Ok(tree_map)
+    }
+
+    // Height of the tree at the given position, if there is one
+    pub fn get(&self, (x, y): (u32, u32)) -> Option<u32> {
+        if x >= self.size_x || y >= self.size_y {
+            return None;
+        }
+
+        Some(self.heights[self.index((x, y))])
+    }
+
+    // Same as `get`, for positions known to be inside the forest
+    fn height(&self, pos: (u32, u32)) -> u32 {
+        self.get(pos).expect("position outside of the forest")
+    }
+
+    pub fn index(&self, (x, y): (u32, u32)) -> usize {
+        (y * self.size_x + x) as usize
+    }
+
+    pub fn positions(&self) -> impl Iterator<Item = (u32, u32)> + '_ {
+        (0..self.size_y).flat_map(move |y| (0..self.size_x).map(move |x| (x, y)))
+    }
+
+    // Indexes of the trees in every row and column, once from each end, in
+    // the order they are seen when looking from that end
+    fn lines_of_sight(&self) -> Vec<Vec<usize>> {
+        let mut lines = vec![];
+
+        for y in 0..self.size_y {
+            let row: Vec<usize> = (0..self.size_x).map(|x| self.index((x, y))).collect();
+            lines.push(row.iter().rev().copied().collect());
+            lines.push(row);
+        }
+
+        for x in 0..self.size_x {
+            let column: Vec<usize> = (0..self.size_y).map(|y| self.index((x, y))).collect();
+            lines.push(column.iter().rev().copied().collect());
+            lines.push(column);
+        }
+
+        lines
+    }
+
+    // Whether each tree can be seen from outside the grid. A tree is visible
+    // from an edge when it's taller than every tree seen before it, so keeping
+    // the tallest height so far along each line is enough: O(n^2) overall.
+    pub fn visibility(&self) -> Vec<bool> {
+        let mut visible = vec![false; self.heights.len()];
+
+        for line in self.lines_of_sight() {
+            let mut tallest = None;
+
+            for index in line {
+                let height = self.heights[index];
+                if tallest.is_none_or(|tallest| height > tallest) {
+                    visible[index] = true;
+                    tallest = Some(height);
+                }
+            }
+        }
+
+        visible
+    }
+
+    // Scenic score of each tree, in O(n^2) overall. Along each line, a stack
+    // keeps the trees that could still block the view of the next ones, from
+    // the shortest on top. Trees shorter than the current one are popped, so
+    // the top of the stack is what blocks its view, or it sees up to the edge.
+    pub fn scenic_scores(&self) -> Vec<u64> {
+        let mut scores = vec![1; self.heights.len()];
+
+        for line in self.lines_of_sight() {
+            let mut stack: Vec<usize> = vec![];
+
+            for (i, &index) in line.iter().enumerate() {
+                let height = self.heights[index];
+                while let Some(&top) = stack.last() {
+                    if self.heights[line[top]] >= height {
+                        break;
+                    }
+                    stack.pop();
+                }
+
+                let viewing_distance = i - stack.last().copied().unwrap_or(0);
+                scores[index] *= viewing_distance as u64;
+                stack.push(i);
+            }
+        }
+
+        scores
+    }
+
+    // The functions below check every direction from every tree, in O(n^3).
+    // They are only used to verify the results of the faster ones.
+
+    pub fn is_tree_visible(&self, pos: (u32, u32)) -> bool {
+        self.is_tree_visible_horizontally(pos) || self.is_tree_visible_vertically(pos)
+    }
+
+    fn is_tree_visible_horizontally(&self, (x, y): (u32, u32)) -> bool {
+        if x == 0 || x == self.size_x - 1 {
+            return true;
+        }
+
+        let tree_height = self.height((x, y));
+        let any_taller_before = (0..x).any(|xx| self.height((xx, y)) >= tree_height);
+        let any_taller_after = (x + 1..self.size_x).any(|xx| self.height((xx, y)) >= tree_height);
+        !(any_taller_before && any_taller_after)
+    }
+
+    fn is_tree_visible_vertically(&self, (x, y): (u32, u32)) -> bool {
+        if y == 0 || y == self.size_y - 1 {
+            return true;
+        }
+
+        let tree_height = self.height((x, y));
+        let any_taller_before = (0..y).any(|yy| self.height((x, yy)) >= tree_height);
+        let any_taller_after = (y + 1..self.size_y).any(|yy| self.height((x, yy)) >= tree_height);
+        !(any_taller_before && any_taller_after)
+    }
+
+    fn is_edge_tree(&self, (x, y): (u32, u32)) -> bool {
+        x == 0 || x == self.size_x - 1 || y == 0 || y == self.size_y - 1
+    }
+
+    pub fn get_scenic_score(&self, (x, y): (u32, u32)) -> u64 {
+        if self.is_edge_tree((x, y)) {
+            return 0;
+        }
+
+        let directions: Vec<Vec<(u32, u32)>> = vec![
+            gen_positions_reverse(y - 1..=0, |y| (x, y)),  // up
+            gen_positions(y + 1..self.size_y, |y| (x, y)), // down
+            gen_positions_reverse(x - 1..=0, |x| (x, y)),  // left
+            gen_positions(x + 1..self.size_x, |x| (x, y)), // right
+        ];
+
+        let mut score = 1;
+        let tree_height = self.height((x, y));
+
+        for direction in directions {
+            let mut count = 0;
+
+            for pos in direction {
+                count += 1;
+                if self.height(pos) >= tree_height {
+                    break;
+                }
+            }
+
+            score *= count;
+        }
+
+        score
+    }
+
+    // Trees on the line going from `from` in steps of `step`, in order. The
+    // step is reduced first, so that no tree on the line is skipped.
+    pub fn line_of_sight(
+        &self,
+        from: (u32, u32),
+        step: (i32, i32),
+    ) -> impl Iterator<Item = (u32, u32)> + '_ {
+        let divisor = gcd(step.0.unsigned_abs(), step.1.unsigned_abs()).max(1) as i32;
+        let (step_x, step_y) = (step.0 / divisor, step.1 / divisor);
+        let (mut x, mut y) = (from.0 as i64, from.1 as i64);
+
+        std::iter::from_fn(move || {
+            if step_x == 0 && step_y == 0 {
+                return None;
+            }
+
+            x += step_x as i64;
+            y += step_y as i64;
+            let inside = x >= 0 && y >= 0 && x < self.size_x as i64 && y < self.size_y as i64;
+            inside.then_some((x as u32, y as u32))
+        })
+    }
+
+    // Trees seen by an observer of the given height looking along a line. The
+    // view stops at the first tree at least as tall as the observer, which is
+    // still seen.
+    pub fn visible_along(
+        &self,
+        from: (u32, u32),
+        step: (i32, i32),
+        height: u32,
+    ) -> Vec<(u32, u32)> {
+        let mut visible = vec![];
+
+        for pos in self.line_of_sight(from, step) {
+            visible.push(pos);
+            if self.height(pos) >= height {
+                break;
+            }
+        }
+
+        visible
+    }
+
+    // Whether the tree at `target` can be seen from the top of the tree at
+    // `observer`, looking in one of the directions allowed by `sight`
+    pub fn is_visible_from(&self, target: (u32, u32), observer: (u32, u32), sight: Sight) -> bool {
+        let step = (
+            target.0 as i32 - observer.0 as i32,
+            target.1 as i32 - observer.1 as i32,
+        );
+        if step == (0, 0) || !sight.allows(step) {
+            return false;
+        }
+
+        let height = self.height(observer);
+        self.line_of_sight(observer, step)
+            .take_while(|&pos| pos != target)
+            .all(|pos| self.height(pos) < height)
+    }
+
+    // How many trees an observer of the given height standing at `(x, y)` can
+    // see, not counting the tree they stand on
+    pub fn count_visible_from(&self, (x, y): (u32, u32), height: u32, sight: Sight) -> usize {
+        sight
+            .steps(self.size_x, self.size_y)
+            .iter()
+            .map(|&step| self.visible_along((x, y), step, height).len())
+            .sum()
+    }
+
+    // Forest with the trees visible from outside in green, and the hidden ones
+    // in grey, brighter for taller trees
+    fn visibility_color(&self, index: usize, visible: &[bool], max_height: u32) -> [u8; 3] {
+        // In u64, as heights given as numbers can be large enough to overflow
+        let brightness = 80 + 175 * u64::from(self.heights[index]) / u64::from(max_height);
+        let brightness = brightness as u8;
+
+        if visible[index] {
+            [30, brightness, 60]
+        } else {
+            [brightness / 2, brightness / 2, brightness / 2]
+        }
+    }
+
+    // Scenic scores go from black to red, yellow and white. They grow
+    // multiplicatively, so a logarithmic scale shows more than a linear one.
+    fn heatmap_color(&self, index: usize, scores: &[u64], best: Option<usize>) -> [u8; 3] {
+        let Some(best) = best else {
+            return [0, 0, 0];
+        };
+        if index == best {
+            return BEST_TREE_COLOR;
+        }
+
+        let max_score = (scores[best] as f64).ln_1p().max(1.0);
+        let heat = (scores[index] as f64).ln_1p() / max_score * 3.0;
+        let channel = |start: f64| ((heat - start).clamp(0.0, 1.0) * 255.0) as u8;
+        [channel(0.0), channel(1.0), channel(2.0)]
+    }
+
+    fn best_tree(&self, scores: &[u64]) -> Option<usize> {
+        let max_score = scores.iter().max()?;
+        scores.iter().position(|score| score == max_score)
+    }
+
+    // Each tree is printed as its height with a 24-bit ANSI background color
+    fn render(&self, color: impl Fn(usize) -> [u8; 3]) -> String {
+        let max_height = self.heights.iter().max().copied().unwrap_or(0);
+        let width = max_height.to_string().len();
+        let mut output = String::new();
+
+        for y in 0..self.size_y {
+            for x in 0..self.size_x {
+                let index = self.index((x, y));
+                let [r, g, b] = color(index);
+                let foreground = if (r as u32 + g as u32 + b as u32) > 384 {
+                    30
+                } else {
+                    97
+                };
+
+                output.push_str(&format!(
+                    "\x1b[{foreground};48;2;{r};{g};{b}m{}",
+                    self.decoder.encode(self.heights[index], width)
+                ));
+            }
+
+            output.push_str("\x1b[0m\n");
+        }
+
+        output
+    }
+
+    fn to_ppm(&self, color: impl Fn(usize) -> [u8; 3]) -> Vec<u8> {
+        let width = self.size_x as usize * PPM_SCALE;
+        let height = self.size_y as usize * PPM_SCALE;
+
+        let mut ppm = format!("P6\n{width} {height}\n255\n").into_bytes();
+
+        for y in 0..self.size_y {
+            let row: Vec<u8> = (0..self.size_x)
+                .flat_map(|x| color(self.index((x, y))).repeat(PPM_SCALE))
+                .collect();
+
+            for _ in 0..PPM_SCALE {
+                ppm.extend(&row);
+            }
+        }
+
+        ppm
+    }
+
+    fn to_csv(&self, visible: &[bool], scores: &[u64]) -> String {
+        let mut csv = String::from("x,y,height,visible,scenic_score\n");
+
+        for (x, y) in self.positions() {
+            let index = self.index((x, y));
+            csv.push_str(&format!(
+                "{x},{y},{},{},{}\n",
+                self.heights[index], visible[index], scores[index]
+            ));
+        }
+
+        csv
+    }
+
+    // Both approaches must always agree
+    pub fn verify(&self, visible: &[bool], scores: &[u64]) {
+        for pos in self.positions() {
+            let index = self.index(pos);
+            assert_eq!(
+                visible[index],
+                self.is_tree_visible(pos),
+                "visibility of {pos:?}"
+            );
+            assert_eq!(
+                scores[index],
+                self.get_scenic_score(pos),
+                "scenic score of {pos:?}"
+            );
+
+            let height = self.height(pos);
+            let along_axes: u64 = Sight::Axes
+                .steps(self.size_x, self.size_y)
+                .iter()
+                .map(|&step| self.visible_along(pos, step, height).len() as u64)
+                .product();
+            assert_eq!(scores[index], along_axes, "lines of sight of {pos:?}");
+        }
+    }
+}
+
+impl Decoder {
+    fn decode(&self, line: &str) -> Result<Vec<u32>, String> {
+        match self {
+            Decoder::Digits => line
+                .chars()
+                .map(|char| char.to_digit(10).ok_or(format!("`{char}` is not a digit")))
+                .collect(),
+            Decoder::Letters => line
+                .chars()
+                .map(|char| match char {
+                    'a'..='z' => Ok(char as u32 - 'a' as u32),
+                    _ => Err(format!("`{char}` is not a letter from a to z")),
+                })
+                .collect(),
+            Decoder::Numbers => line
+                .split(|char: char| char == ',' || char.is_whitespace())
+                .filter(|number| !number.is_empty())
+                .map(|number| {
+                    number
+                        .parse()
+                        .map_err(|_| format!("`{number}` is not a height"))
+                })
+                .collect(),
+        }
+    }
+}
+
+impl Decoder {
+    // Height written the same way as in the input. Numbers are padded to
+    // `width` digits, with a space on each side, so that the columns line up.
+    fn encode(&self, height: u32, width: usize) -> String {
+        match self {
+            Decoder::Digits => height.to_string(),
+            Decoder::Letters => char::from(b'a' + height as u8).to_string(),
+            Decoder::Numbers => format!(" {height:>width$} "),
+        }
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(
+            f,
+            "Invalid forest on line {}: {}",
+            self.line_number, self.message
+        )?;
+        write!(f, "  {}", self.line)
+    }
+}
+
+impl Sight {
+    fn allows(&self, (x, y): (i32, i32)) -> bool {
+        match self {
+            Sight::Axes => x == 0 || y == 0,
+            Sight::Compass => x == 0 || y == 0 || x.abs() == y.abs(),
+            Sight::Any => true,
+        }
+    }
+
+    // One step for each direction, reduced. For `Sight::Any`, these are enough
+    // to go through every tree of a grid of the given size.
+    fn steps(&self, size_x: u32, size_y: u32) -> Vec<(i32, i32)> {
+        let axes = [(0, -1), (0, 1), (-1, 0), (1, 0)];
+        let diagonals = [(-1, -1), (1, -1), (-1, 1), (1, 1)];
+
+        match self {
+            Sight::Axes => axes.to_vec(),
+            Sight::Compass => [axes, diagonals].concat(),
+            Sight::Any => {
+                let (max_x, max_y) = (size_x as i32, size_y as i32);
+                let mut steps = vec![];
+
+                for x in -max_x..=max_x {
+                    for y in -max_y..=max_y {
+                        if gcd(x.unsigned_abs(), y.unsigned_abs()) == 1 {
+                            steps.push((x, y));
+                        }
+                    }
+                }
+
+                steps
+            }
+        }
+    }
+}
+
+fn gcd(a: u32, b: u32) -> u32 {
+    if b == 0 {
+        a
+    } else {
+        gcd(b, a % b)
+    }
+}
+
+fn gen_positions_reverse<F: Fn(u32) -> (u32, u32)>(
+    range: RangeInclusive<u32>,
+    fun: F,
+) -> Vec<(u32, u32)> {
+    (*range.end()..=*range.start()).rev().map(fun).collect()
+}
+
+fn gen_positions<F: Fn(u32) -> (u32, u32)>(range: Range<u32>, fun: F) -> Vec<(u32, u32)> {
+    range.map(fun).collect()
+}
+
+impl Options {
+    pub fn from_args() -> Options {
+        let mut options = Options {
+            verify: false,
+            show_visible: false,
+            show_heatmap: false,
+            visible_ppm: None,
+            heatmap_ppm: None,
+            csv: None,
+            observer: None,
+            visible_from: None,
+            sight: Sight::Axes,
+            decoder: Decoder::Digits,
+        };
+
+        let mut args = env::args().skip(1);
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--verify" => options.verify = true,
+                "--visible" => options.show_visible = true,
+                "--heatmap" => options.show_heatmap = true,
+                "--visible-ppm" => {
+                    options.visible_ppm = Some(args.next().expect("--visible-ppm expects a file"))
+                }
+                "--heatmap-ppm" => {
+                    options.heatmap_ppm = Some(args.next().expect("--heatmap-ppm expects a file"))
+                }
+                "--csv" => options.csv = Some(args.next().expect("--csv expects a file")),
+                "--observer" => options.observer = Some(parse_observer(args.next())),
+                "--visible-from" => options.visible_from = Some(parse_trees(args.next())),
+                "--heights" => {
+                    options.decoder = match args.next().as_deref() {
+                        Some("digits") => Decoder::Digits,
+                        Some("letters") => Decoder::Letters,
+                        Some("numbers") => Decoder::Numbers,
+                        _ => panic!("--heights expects one of digits, letters or numbers"),
+                    }
+                }
+                "--sight" => {
+                    options.sight = match args.next().as_deref() {
+                        Some("axes") => Sight::Axes,
+                        Some("compass") => Sight::Compass,
+                        Some("any") => Sight::Any,
+                        _ => panic!("--sight expects one of axes, compass or any"),
+                    }
+                }
+                _ => panic!("Unknown option {arg}"),
+            }
+        }
+
+        options
+    }
+
+    // Checks, prints and writes whatever the options ask for
+    pub fn output(&self, tree_map: &TreeMap, visible: &[bool], scores: &[u64]) {
+        let max_height = tree_map.heights.iter().max().copied().unwrap_or(0).max(1);
+        let best = tree_map.best_tree(scores);
+        let visible_color = |index| tree_map.visibility_color(index, visible, max_height);
+        let heatmap_color = |index| tree_map.heatmap_color(index, scores, best);
+
+        if self.verify {
+            tree_map.verify(visible, scores);
+        }
+
+        if self.show_visible {
+            print!("\n{}", tree_map.render(visible_color));
+        }
+
+        if self.show_heatmap {
+            print!("\n{}", tree_map.render(heatmap_color));
+
+            if let Some(best) = best {
+                let (x, y) = (best as u32 % tree_map.size_x, best as u32 / tree_map.size_x);
+                println!(
+                    "Best tree at {x},{y} with a scenic score of {}",
+                    scores[best]
+                );
+            }
+        }
+
+        if let Some(((x, y), height)) = self.observer {
+            if x >= tree_map.size_x || y >= tree_map.size_y {
+                panic!("The observer at {x},{y} is outside of the forest");
+            }
+
+            let count = tree_map.count_visible_from((x, y), height, self.sight);
+            println!("\nAn observer at {x},{y} with a height of {height} sees {count} trees");
+        }
+
+        if let Some((observer, target)) = self.visible_from {
+            for (x, y) in [observer, target] {
+                if x >= tree_map.size_x || y >= tree_map.size_y {
+                    panic!("The tree at {x},{y} is outside of the forest");
+                }
+            }
+
+            let seen = tree_map.is_visible_from(target, observer, self.sight);
+            println!(
+                "\nThe tree at {},{} {} be seen from the top of the tree at {},{}",
+                target.0,
+                target.1,
+                if seen { "can" } else { "can't" },
+                observer.0,
+                observer.1
+            );
+        }
+
+        if let Some(file) = &self.visible_ppm {
+            fs::write(file, tree_map.to_ppm(visible_color)).unwrap();
+        }
+
+        if let Some(file) = &self.heatmap_ppm {
+            fs::write(file, tree_map.to_ppm(heatmap_color)).unwrap();
+        }
+
+        if let Some(file) = &self.csv {
+            fs::write(file, tree_map.to_csv(visible, scores)).unwrap();
+        }
+    }
+}
+
+// Observers are given as X,Y,HEIGHT
+fn parse_observer(value: Option<String>) -> ((u32, u32), u32) {
+    let numbers: Option<Vec<u32>> = value
+        .as_deref()
+        .map(|value| value.split(',').map(|n| n.parse().ok()).collect())
+        .unwrap_or(None);
+
+    match numbers.as_deref() {
+        Some(&[x, y, height]) => ((x, y), height),
+        _ => panic!("--observer expects a position and a height like 3,2,5"),
+    }
+}
+
+// Pairs of trees are given as X,Y,X,Y
+fn parse_trees(value: Option<String>) -> ((u32, u32), (u32, u32)) {
+    let numbers: Option<Vec<u32>> = value
+        .as_deref()
+        .map(|value| value.split(',').map(|n| n.parse().ok()).collect())
+        .unwrap_or(None);
+
+    match numbers.as_deref() {
+        Some(&[x, y, target_x, target_y]) => ((x, y), (target_x, target_y)),
+        _ => panic!("--visible-from expects two positions like 2,2,4,0"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EXAMPLE: &str = "30373\n25512\n65332\n33549\n35390\n";
+
+    // The linear passes must agree with looking around each tree
+    fn check(input: &str) {
+        let tree_map = TreeMap::from_str(input, Decoder::Digits).unwrap();
+        let visible = tree_map.visibility();
+        let scores = tree_map.scenic_scores();
+
+        for pos in tree_map.positions() {
+            let index = tree_map.index(pos);
+            assert_eq!(
+                visible[index],
+                tree_map.is_tree_visible(pos),
+                "visibility of {pos:?}"
+            );
+            assert_eq!(
+                scores[index],
+                tree_map.get_scenic_score(pos),
+                "scenic score of {pos:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn example() {
+        let tree_map = TreeMap::from_str(EXAMPLE, Decoder::Digits).unwrap();
+        let visible = tree_map.visibility();
+
+        assert_eq!(visible.iter().filter(|&&visible| visible).count(), 21);
+        assert_eq!(tree_map.scenic_scores().into_iter().max(), Some(8));
+        check(EXAMPLE);
+    }
+
+    #[test]
+    fn non_square() {
+        check("3037\n2551\n6533\n");
+        check("30373025\n25512413\n");
+        check("3\n2\n6\n3\n3\n");
+        check("1\n");
+        check("55555\n");
+    }
+
+    #[test]
+    fn random_grids() {
+        let mut seed: u32 = 12345;
+        let mut digit = || {
+            seed = seed.wrapping_mul(1103515245).wrapping_add(12345);
+            char::from(b'0' + (seed >> 16) as u8 % 10)
+        };
+
+        for (size_x, size_y) in [(7, 13), (13, 7), (20, 3), (1, 9), (16, 16)] {
+            let input: String = (0..size_y)
+                .map(|_| {
+                    (0..size_x)
+                        .map(|_| digit())
+                        .chain(['\n'])
+                        .collect::<String>()
+                })
+                .collect();
+            check(&input);
+        }
+    }
+
+    #[test]
+    fn line_of_sight_steps() {
+        let tree_map = TreeMap::from_str("1234567\n".repeat(5).as_str(), Decoder::Digits).unwrap();
+        let line = |step| tree_map.line_of_sight((0, 0), step).collect::<Vec<_>>();
+
+        assert_eq!(line((4, 2)), [(2, 1), (4, 2), (6, 3)]);
+        assert_eq!(line((2, 1)), line((4, 2)));
+        assert_eq!(line((0, 3)), [(0, 1), (0, 2), (0, 3), (0, 4)]);
+        assert_eq!(line((-1, 1)), []);
+        assert_eq!(line((0, 0)), []);
+    }
+
+    #[test]
+    fn count_visible_from() {
+        let tree_map = TreeMap::from_str(EXAMPLE, Decoder::Digits).unwrap();
+
+        // Hidden behind the 5s around the middle tree: 2,0, 2,4, 0,2 and 0,0
+        assert_eq!(tree_map.count_visible_from((2, 2), 5, Sight::Axes), 5);
+        assert_eq!(tree_map.count_visible_from((2, 2), 5, Sight::Compass), 12);
+        assert_eq!(tree_map.count_visible_from((2, 2), 5, Sight::Any), 20);
+
+        // From a corner, the 3 at 2,0, the 6 at 0,2 and the 5 at 1,1 block the
+        // view, and every other line of sight leaves the forest after one tree
+        assert_eq!(tree_map.count_visible_from((0, 0), 3, Sight::Axes), 4);
+        assert_eq!(tree_map.count_visible_from((0, 0), 3, Sight::Compass), 5);
+        assert_eq!(tree_map.count_visible_from((0, 0), 3, Sight::Any), 15);
+    }
+
+    #[test]
+    fn is_visible_from() {
+        let tree_map = TreeMap::from_str(EXAMPLE, Decoder::Digits).unwrap();
+
+        // The middle tree is a 3, the 1 at 3,1 is shorter and the 5 at 1,1 isn't
+        assert!(!tree_map.is_visible_from((4, 0), (2, 2), Sight::Axes));
+        assert!(tree_map.is_visible_from((4, 0), (2, 2), Sight::Compass));
+        assert!(!tree_map.is_visible_from((0, 0), (2, 2), Sight::Compass));
+        assert!(tree_map.is_visible_from((1, 1), (2, 2), Sight::Compass));
+        assert!(!tree_map.is_visible_from((0, 3), (2, 2), Sight::Compass));
+        assert!(tree_map.is_visible_from((0, 3), (2, 2), Sight::Any));
+        assert!(!tree_map.is_visible_from((2, 2), (2, 2), Sight::Any));
+    }
+
+    // Rendered forest without the colors
+    fn render_plain(input: &str, decoder: Decoder) -> String {
+        let rendered = TreeMap::from_str(input, decoder)
+            .unwrap()
+            .render(|_| [0, 0, 0]);
+        let mut plain = String::new();
+        let mut chars = rendered.chars();
+
+        while let Some(char) = chars.next() {
+            if char == '\x1b' {
+                chars.by_ref().find(|&char| char == 'm');
+            } else {
+                plain.push(char);
+            }
+        }
+
+        plain
+    }
+
+    #[test]
+    fn render_in_the_input_encoding() {
+        assert_eq!(render_plain(EXAMPLE, Decoder::Digits), EXAMPLE);
+        assert_eq!(
+            render_plain("dagcb\nzyaaa\n", Decoder::Letters),
+            "dagcb\nzyaaa\n"
+        );
+        assert_eq!(
+            render_plain("100 5\n37,3\n", Decoder::Numbers),
+            " 100    5 \n  37    3 \n"
+        );
+    }
+
+    #[test]
+    fn colors_of_tall_trees() {
+        let tree_map = TreeMap::from_str("100000000 5\n7 3\n", Decoder::Numbers).unwrap();
+        let visible = tree_map.visibility();
+
+        assert_eq!(
+            tree_map.visibility_color(0, &visible, 100000000),
+            [30, 255, 60]
+        );
+        assert_eq!(
+            tree_map.visibility_color(3, &visible, 100000000),
+            [30, 80, 60]
+        );
+    }
+}